@@ -0,0 +1,53 @@
+//!Benchmarks of the core primitives.
+//!
+//!Run with `cargo bench --features bench`. Each group times a call
+//!with inputs that are equal and inputs that differ, the two should
+//!be indistinguishable.
+
+#[macro_use]
+extern crate criterion;
+extern crate consistenttime;
+
+use criterion::{Criterion, black_box};
+use consistenttime::*;
+
+fn scalar(c: &mut Criterion) {
+    c.bench_function("ct_u64_eq equal", |b| {
+        b.iter(|| ct_u64_eq(black_box(0xDEADBEEF), black_box(0xDEADBEEF)))
+    });
+    c.bench_function("ct_u64_eq differ", |b| {
+        b.iter(|| ct_u64_eq(black_box(0xDEADBEEF), black_box(0)))
+    });
+    c.bench_function("ct_select_u64 true", |b| {
+        b.iter(|| ct_select_u64(black_box(true), black_box(1), black_box(2)))
+    });
+    c.bench_function("ct_select_u64 false", |b| {
+        b.iter(|| ct_select_u64(black_box(false), black_box(1), black_box(2)))
+    });
+}
+
+fn slices(c: &mut Criterion) {
+    let x = vec![0x5Au8; 64 * 1024];
+    let mut y = x.clone();
+    c.bench_function("ct_u8_slice_eq 64KiB equal", |b| {
+        b.iter(|| ct_u8_slice_eq(black_box(&x), black_box(&y)))
+    });
+    y[0] ^= 1;
+    c.bench_function("ct_u8_slice_eq 64KiB differ", |b| {
+        b.iter(|| ct_u8_slice_eq(black_box(&x), black_box(&y)))
+    });
+    let mut dst = vec![0u8; 64 * 1024];
+    c.bench_function("ct_copy_u8 64KiB true", |b| {
+        b.iter(|| ct_copy_u8(black_box(true), &mut dst, black_box(&x)))
+    });
+    c.bench_function("ct_copy_u8 64KiB false", |b| {
+        b.iter(|| ct_copy_u8(black_box(false), &mut dst, black_box(&x)))
+    });
+    let table = [0u32; 256];
+    c.bench_function("ct_lookup 256", |b| {
+        b.iter(|| ct_lookup(black_box(&table), black_box(17)))
+    });
+}
+
+criterion_group!(benches, scalar, slices);
+criterion_main!(benches);
@@ -0,0 +1,70 @@
+//Copyright 2016 William Cody Laeder
+//
+//Licensed under the Apache License, Version 2.0 (the "License");
+//you may not use this file except in compliance with the License.
+//you may obtain a copy of the License at
+//
+//  http://www.apache.org/licenses/LICENSE-2.0
+//
+//Unless required by applicable law or agreed to in writing, software
+//distrubuted under the License is distrubuted on as "AS IS" BASIS,
+//WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//See the License for the specific language governing permissions and
+//limitations under the License.
+
+
+//!AES helpers
+//!
+//!The AES S-box computed rather than looked up. A 256 entry table
+//!indexed by key dependent bytes is the classic cache timing leak in
+//!software AES, here SubBytes is only ever arithmetic.
+
+use super::arith::ct_gf256_mul;
+
+/*
+ * Inversion in GF(2^8) as x^254, with 0 going to 0 for free.
+ * x^127 is built by squaring and multiplying by x six times, one more
+ * squaring gives x^254.
+ */
+fn gf256_inv(x: u8) -> u8 {
+    let mut r = x;
+    for _ in 0..6 {
+        r = ct_gf256_mul(ct_gf256_mul(r, r), x);
+    }
+    ct_gf256_mul(r, r)
+}
+
+///AES SubBytes of a single byte.
+///
+///The multiplicative inverse in GF(2^8) followed by the affine
+///transform of FIPS-197 section 5.1.1. Takes the same fixed sequence
+///of operations for every input.
+pub fn ct_aes_sbox(x: u8) -> u8 {
+    let b = gf256_inv(x);
+    b ^ b.rotate_left(1) ^ b.rotate_left(2) ^ b.rotate_left(3) ^ b.rotate_left(4) ^ 0x63
+}
+///AES SubBytes over a buffer, in place.
+pub fn ct_aes_sub_bytes(x: &mut [u8]) {
+    for val in x.iter_mut() {
+        *val = ct_aes_sbox(*val);
+    }
+}
+#[test]
+fn test_ct_aes_sbox() {
+    //FIPS-197 figure 7
+    let known: [(u8,u8);6] = [(0x00,0x63), (0x01,0x7C), (0x10,0xCA),
+        (0x53,0xED), (0x9A,0xB8), (0xFF,0x16)];
+    for &(x, y) in known.iter() {
+        assert_eq!( ct_aes_sbox(x), y);
+    }
+    let mut seen = [false;256];
+    for x in 0..256 {
+        let y = ct_aes_sbox(x as u8) as usize;
+        assert_eq!( seen[y], false);
+        seen[y] = true;
+    }
+    //FIPS-197 appendix B, round 1 SubBytes of the first column
+    let mut col: [u8;4] = [0x19, 0x3D, 0xE3, 0xBE];
+    ct_aes_sub_bytes(&mut col);
+    assert_eq!( col, [0xD4, 0x27, 0x11, 0xAE]);
+}
@@ -0,0 +1,162 @@
+//Copyright 2016 William Cody Laeder
+//
+//Licensed under the Apache License, Version 2.0 (the "License");
+//you may not use this file except in compliance with the License.
+//you may obtain a copy of the License at
+//
+//  http://www.apache.org/licenses/LICENSE-2.0
+//
+//Unless required by applicable law or agreed to in writing, software
+//distrubuted under the License is distrubuted on as "AS IS" BASIS,
+//WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//See the License for the specific language governing permissions and
+//limitations under the License.
+
+
+/*
+ * Target specific vector kernels, selected at compile time.
+ *
+ * Each kernel handles as many whole vectors as fit and returns how
+ * many bytes it processed, the caller finishes the tail with scalar
+ * code. Without a kernel for the target nothing is processed.
+ *
+ * The mask is 0xFF or 0x00 and is splatted across a vector, every
+ * lane is blended reguardless of its value.
+ *
+ * The kernels are safe functions doing raw vector loads, so each one
+ * bounds itself by the shorter of its two slices rather than trusting
+ * the caller to have checked the lengths.
+ */
+
+#[cfg(all(target_arch = "x86_64", target_feature = "avx2"))]
+pub fn copy_u8(mask: u8, x: &mut [u8], y: &[u8]) -> usize {
+    use core::arch::x86_64::*;
+    let len = ::core::cmp::min(x.len(), y.len());
+    let mut i = 0;
+    unsafe {
+        let wide = _mm256_set1_epi8(mask as i8);
+        while i + 32 <= len {
+            let x_val = _mm256_loadu_si256(x.as_ptr().add(i) as *const __m256i);
+            let y_val = _mm256_loadu_si256(y.as_ptr().add(i) as *const __m256i);
+            let val = _mm256_blendv_epi8(x_val, y_val, wide);
+            _mm256_storeu_si256(x.as_mut_ptr().add(i) as *mut __m256i, val);
+            i += 32;
+        }
+    }
+    i
+}
+
+#[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+pub fn copy_u8(mask: u8, x: &mut [u8], y: &[u8]) -> usize {
+    use core::arch::aarch64::*;
+    let len = ::core::cmp::min(x.len(), y.len());
+    let mut i = 0;
+    unsafe {
+        let wide = vdupq_n_u8(mask);
+        while i + 16 <= len {
+            let x_val = vld1q_u8(x.as_ptr().add(i));
+            let y_val = vld1q_u8(y.as_ptr().add(i));
+            let val = vbslq_u8(wide, y_val, x_val);
+            vst1q_u8(x.as_mut_ptr().add(i), val);
+            i += 16;
+        }
+    }
+    i
+}
+
+#[cfg(all(feature = "wasm-simd", target_arch = "wasm32", target_feature = "simd128"))]
+pub fn copy_u8(mask: u8, x: &mut [u8], y: &[u8]) -> usize {
+    use core::arch::wasm32::*;
+    let len = ::core::cmp::min(x.len(), y.len());
+    let mut i = 0;
+    unsafe {
+        let wide = u8x16_splat(mask);
+        while i + 16 <= len {
+            let x_val = v128_load(x.as_ptr().add(i) as *const v128);
+            let y_val = v128_load(y.as_ptr().add(i) as *const v128);
+            let val = v128_bitselect(y_val, x_val, wide);
+            v128_store(x.as_mut_ptr().add(i) as *mut v128, val);
+            i += 16;
+        }
+    }
+    i
+}
+
+#[cfg(not(any(
+    all(target_arch = "x86_64", target_feature = "avx2"),
+    all(target_arch = "aarch64", target_feature = "neon"),
+    all(feature = "wasm-simd", target_arch = "wasm32", target_feature = "simd128"))))]
+pub fn copy_u8(_mask: u8, _x: &mut [u8], _y: &[u8]) -> usize {
+    0
+}
+
+/*
+ * Equality kernels return how many bytes they processed and if those
+ * bytes were equal. The lanes are OR'd into one accumulator and only
+ * folded down once, after the loop.
+ */
+#[cfg(all(feature = "wasm-simd", target_arch = "wasm32", target_feature = "simd128"))]
+pub fn eq_u8(x: &[u8], y: &[u8]) -> (usize, bool) {
+    use core::arch::wasm32::*;
+    let len = ::core::cmp::min(x.len(), y.len());
+    let mut i = 0;
+    let flag = unsafe {
+        let mut acc = u8x16_splat(0);
+        while i + 16 <= len {
+            let x_val = v128_load(x.as_ptr().add(i) as *const v128);
+            let y_val = v128_load(y.as_ptr().add(i) as *const v128);
+            acc = v128_or(acc, v128_xor(x_val, y_val));
+            i += 16;
+        }
+        u64x2_extract_lane::<0>(acc) | u64x2_extract_lane::<1>(acc)
+    };
+    (i, super::ct_u64_eq(flag, 0))
+}
+
+#[cfg(not(all(feature = "wasm-simd", target_arch = "wasm32", target_feature = "simd128")))]
+pub fn eq_u8(_x: &[u8], _y: &[u8]) -> (usize, bool) {
+    (0, true)
+}
+
+#[cfg(all(feature = "wasm-simd", target_arch = "wasm32", target_feature = "simd128"))]
+pub fn xor_u8(x: &mut [u8], y: &[u8]) -> usize {
+    use core::arch::wasm32::*;
+    let len = ::core::cmp::min(x.len(), y.len());
+    let mut i = 0;
+    unsafe {
+        while i + 16 <= len {
+            let x_val = v128_load(x.as_ptr().add(i) as *const v128);
+            let y_val = v128_load(y.as_ptr().add(i) as *const v128);
+            v128_store(x.as_mut_ptr().add(i) as *mut v128, v128_xor(x_val, y_val));
+            i += 16;
+        }
+    }
+    i
+}
+
+#[cfg(not(all(feature = "wasm-simd", target_arch = "wasm32", target_feature = "simd128")))]
+pub fn xor_u8(_x: &mut [u8], _y: &[u8]) -> usize {
+    0
+}
+
+/*
+ * Software prefetch of the cache line holding `ptr`, a hint only.
+ * Prefetching never faults, so `ptr` may be past the end of a slice.
+ */
+#[cfg(target_arch = "x86_64")]
+#[inline(always)]
+pub fn prefetch<T>(ptr: *const T) {
+    use core::arch::x86_64::*;
+    unsafe{ _mm_prefetch::<_MM_HINT_T0>(ptr as *const i8) };
+}
+
+#[cfg(target_arch = "aarch64")]
+#[inline(always)]
+pub fn prefetch<T>(ptr: *const T) {
+    unsafe{ core::arch::asm!("prfm pldl1keep, [{0}]", in(reg) ptr, options(nostack, readonly, preserves_flags)) };
+}
+
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+#[inline(always)]
+pub fn prefetch<T>(_ptr: *const T) {
+}
@@ -0,0 +1,590 @@
+//Copyright 2016 William Cody Laeder
+//
+//Licensed under the Apache License, Version 2.0 (the "License");
+//you may not use this file except in compliance with the License.
+//you may obtain a copy of the License at
+//
+//  http://www.apache.org/licenses/LICENSE-2.0
+//
+//Unless required by applicable law or agreed to in writing, software
+//distrubuted under the License is distrubuted on as "AS IS" BASIS,
+//WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//See the License for the specific language governing permissions and
+//limitations under the License.
+
+
+//!Scalar arithmetic
+//!
+//!Branch free arithmetic on single words, the building blocks for
+//!constant time multi precision code. Carries come from widening
+//!arithmetic and every comparison is turned into a mask, nothing here
+//!branches on an operand.
+
+use super::{ct_u32_lt, ct_u64_lt, ct_u32_eq, ct_u64_eq, ct_select_u32, ct_select_u64};
+
+macro_rules! ct_adc_gen {
+    ($adc:ident,$sbb:ident,$code:ident,$wide:ident,$bits:expr
+    ;;$test_adc:ident,$test_sbb:ident) => {
+        ///Add with carry.
+        ///
+        ///Returns `(x + y + carry) mod 2^BITS` and the carry out, which
+        ///is `0` or `1`. `carry` should be `0` or `1`.
+        pub fn $adc(x: $code, y: $code, carry: $code) -> ($code, $code) {
+            let t = (x as $wide) + (y as $wide) + (carry as $wide);
+            (t as $code, (t >> $bits) as $code)
+        }
+        ///Subtract with borrow.
+        ///
+        ///Returns `(x - y - borrow) mod 2^BITS` and the borrow out,
+        ///which is `0` or `1`. `borrow` should be `0` or `1`.
+        pub fn $sbb(x: $code, y: $code, borrow: $code) -> ($code, $code) {
+            let t = (x as $wide).wrapping_sub((y as $wide) + (borrow as $wide));
+            (t as $code, (t >> (2 * $bits - 1)) as $code)
+        }
+        #[test]
+        fn $test_adc() {
+            const MAX: $code = max!($code);
+            assert_eq!( $adc(1, 2, 0), (3, 0));
+            assert_eq!( $adc(1, 2, 1), (4, 0));
+            assert_eq!( $adc(MAX, 1, 0), (0, 1));
+            assert_eq!( $adc(MAX, 0, 1), (0, 1));
+            assert_eq!( $adc(MAX, MAX, 1), (MAX, 1));
+            assert_eq!( $adc(MAX, MAX, 0), (MAX - 1, 1));
+        }
+        #[test]
+        fn $test_sbb() {
+            const MAX: $code = max!($code);
+            assert_eq!( $sbb(3, 2, 0), (1, 0));
+            assert_eq!( $sbb(3, 2, 1), (0, 0));
+            assert_eq!( $sbb(0, 1, 0), (MAX, 1));
+            assert_eq!( $sbb(0, 0, 1), (MAX, 1));
+            assert_eq!( $sbb(0, MAX, 1), (0, 1));
+            assert_eq!( $sbb(MAX, MAX, 0), (0, 0));
+        }
+    }
+}
+ct_adc_gen!(ct_adc_u32,ct_sbb_u32,u32,u64,32;;
+    test_ct_adc_u32,test_ct_sbb_u32);
+ct_adc_gen!(ct_adc_u64,ct_sbb_u64,u64,u128,64;;
+    test_ct_adc_u64,test_ct_sbb_u64);
+
+/*
+ * Multiplication is only as constant time as the hardware multiplier.
+ * Full width MUL/UMULH on x86_64 and aarch64 do not exit early. Some
+ * small cores (ARM7TDMI, Cortex-M3, old PowerPC) terminate a multiply
+ * early when the upper bits of an operand are zero, nothing in this
+ * crate can fix that.
+ *
+ * What these do guarantee is that the widening product is computed
+ * by a single full width multiply (or the runtime's fixed sequence of
+ * half width ones) with no shortcuts for small operands, and that
+ * LLVM can not see through the call to specialize on known values.
+ */
+macro_rules! ct_mul_gen {
+    ($mul:ident,$mulhi:ident,$code:ident,$wide:ident,$bits:expr
+    ;;$test_name:ident) => {
+        ///Widening multiplication, returns the full double width product.
+        #[inline(never)]
+        pub fn $mul(x: $code, y: $code) -> $wide {
+            (x as $wide) * (y as $wide)
+        }
+        ///High half of the widening product.
+        #[inline(never)]
+        pub fn $mulhi(x: $code, y: $code) -> $code {
+            (((x as $wide) * (y as $wide)) >> $bits) as $code
+        }
+        #[test]
+        fn $test_name() {
+            const MAX: $code = max!($code);
+            assert_eq!( $mul(0, MAX), 0);
+            assert_eq!( $mul(1, MAX), MAX as $wide);
+            assert_eq!( $mul(MAX, MAX), (MAX as $wide) * (MAX as $wide));
+            assert_eq!( $mulhi(MAX, MAX), MAX - 1);
+            assert_eq!( $mulhi(MAX, 2), 1);
+            assert_eq!( $mulhi(MAX, 1), 0);
+            assert_eq!( $mulhi(1 << ($bits - 1), 4), 2);
+        }
+    }
+}
+ct_mul_gen!(ct_widening_mul_u32,ct_mulhi_u32,u32,u64,32;;
+    test_ct_mul_u32);
+ct_mul_gen!(ct_widening_mul_u64,ct_mulhi_u64,u64,u128,64;;
+    test_ct_mul_u64);
+
+/*
+ * Restoring division, one quotient bit per iteration from the top.
+ * The partial remainder is shifted left, the bit shifted out of the
+ * top is remembered, and y is subtracted under a mask when it fits.
+ * The loop always runs BITS times and every iteration does the same
+ * work.
+ *
+ * Division by zero does not panic, a branch on a secret divisor
+ * would defeat the point. Every step "fits", so the quotient is all
+ * ones and the remainder is x.
+ */
+macro_rules! ct_div_gen {
+    ($div:ident,$rem:ident,$div_rem:ident,$code:ident,$lt:ident,$select:ident,$bits:expr
+    ;;$test_name:ident) => {
+        ///Constant time division and remainder, returns `(x / y, x % y)`.
+        ///
+        ///Both come out of the same restoring division, see `ct_div`.
+        ///
+        ///Dividing by zero returns the all ones value and `x` instead of
+        ///panicking.
+        pub fn $div_rem(x: $code, y: $code) -> ($code, $code) {
+            let mut q: $code = 0;
+            let mut r: $code = 0;
+            for i in (0..$bits).rev() {
+                let top = unsafe{::core::mem::transmute::<u8,bool>((r >> ($bits - 1)) as u8)};
+                r = (r << 1) | ((x >> i) & 1);
+                let fits = top | !$lt(r, y);
+                r = $select(fits, r.wrapping_sub(y), r);
+                let bit = unsafe{::core::mem::transmute::<bool,u8>(fits)};
+                q |= (bit as $code) << i;
+            }
+            (q, r)
+        }
+        ///Constant time division, returns `x / y`.
+        ///
+        ///Bit by bit restoring division with masked updates, the time
+        ///taken does not depend on either operand. Hardware division
+        ///is variable latency on most processors.
+        ///
+        ///Dividing by zero returns the all ones value instead of
+        ///panicking.
+        pub fn $div(x: $code, y: $code) -> $code {
+            $div_rem(x, y).0
+        }
+        ///Constant time remainder, returns `x % y`.
+        ///
+        ///Use this instead of `%` for secret moduli, see `ct_div`.
+        ///
+        ///The remainder of a division by zero is `x`.
+        pub fn $rem(x: $code, y: $code) -> $code {
+            $div_rem(x, y).1
+        }
+        #[test]
+        fn $test_name() {
+            const MAX: $code = max!($code);
+            let vals: [$code;9] = [0, 1, 2, 3, 7, 10, 255, MAX - 1, MAX];
+            for &x in vals.iter() {
+                for &y in vals.iter() {
+                    if y != 0 {
+                        assert_eq!( $div(x, y), x / y);
+                        assert_eq!( $rem(x, y), x % y);
+                        assert_eq!( $div_rem(x, y), (x / y, x % y));
+                    }
+                }
+                assert_eq!( $div_rem(x, 0), (MAX, x));
+                assert_eq!( $rem(x, 0), x);
+            }
+            assert_eq!( $div(1 << ($bits - 1), 3), (1 << ($bits - 1)) / 3);
+        }
+    }
+}
+ct_div_gen!(ct_div_u32,ct_rem_u32,ct_div_rem_u32,u32,ct_u32_lt,ct_select_u32,32;;
+    test_ct_div_u32);
+ct_div_gen!(ct_div_u64,ct_rem_u64,ct_div_rem_u64,u64,ct_u64_lt,ct_select_u64,64;;
+    test_ct_div_u64);
+
+macro_rules! ct_mod_gen {
+    ($add_mod:ident,$sub_mod:ident,$code:ident,$adc:ident,$sbb:ident,$select:ident
+    ;;$test_name:ident,$m:expr) => {
+        ///Modular addition, returns `(x + y) mod m`.
+        ///
+        ///`x` and `y` must already be reduced, below `m`. The sum is
+        ///computed with its carry, `m` is always subtracted, and the
+        ///subtraction is kept or discarded with a mask.
+        pub fn $add_mod(x: $code, y: $code, m: $code) -> $code {
+            let (sum, carry) = $adc(x, y, 0);
+            let (diff, borrow) = $sbb(sum, m, 0);
+            let keep = carry | (borrow ^ 1);
+            let keep = unsafe{::core::mem::transmute::<u8,bool>(keep as u8)};
+            $select(keep, diff, sum)
+        }
+        ///Modular subtraction, returns `(x - y) mod m`.
+        ///
+        ///`x` and `y` must already be reduced, below `m`. `m` is always
+        ///added back to the difference, and the correction is kept or
+        ///discarded with a mask.
+        pub fn $sub_mod(x: $code, y: $code, m: $code) -> $code {
+            let (diff, borrow) = $sbb(x, y, 0);
+            let (fixed, _) = $adc(diff, m, 0);
+            let borrow = unsafe{::core::mem::transmute::<u8,bool>(borrow as u8)};
+            $select(borrow, fixed, diff)
+        }
+        #[test]
+        fn $test_name() {
+            const MAX: $code = max!($code);
+            let moduli: [$code;4] = [$m, 7, MAX, (MAX >> 1) + 3];
+            for &m in moduli.iter() {
+                let vals: [$code;5] = [0, 1, m / 2, m - 2, m - 1];
+                for &x in vals.iter() {
+                    for &y in vals.iter() {
+                        let want_add = (((x as u128) + (y as u128)) % (m as u128)) as $code;
+                        let want_sub = (((x as u128) + (m as u128) - (y as u128)) % (m as u128)) as $code;
+                        assert_eq!( $add_mod(x, y, m), want_add);
+                        assert_eq!( $sub_mod(x, y, m), want_sub);
+                    }
+                }
+            }
+        }
+    }
+}
+ct_mod_gen!(ct_add_mod_u32,ct_sub_mod_u32,u32,ct_adc_u32,ct_sbb_u32,ct_select_u32;;
+    test_ct_mod_u32,0xFFFFFFFB);
+ct_mod_gen!(ct_add_mod_u64,ct_sub_mod_u64,u64,ct_adc_u64,ct_sbb_u64,ct_select_u64;;
+    test_ct_mod_u64,0xFFFFFFFFFFFFFFC5);
+
+macro_rules! ct_neg_mod_gen {
+    ($name:ident,$code:ident,$eq:ident,$select:ident;;$test_name:ident) => {
+        ///Conditional modular negation.
+        ///
+        ///Returns `(m - x) mod m` if `flag` is true, otherwise `x`. `x`
+        ///must already be reduced, below `m`. Zero negates to zero, this
+        ///is folded into the mask rather than branched on.
+        pub fn $name(flag: bool, x: $code, m: $code) -> $code {
+            let neg = m.wrapping_sub(x);
+            let neg = $select($eq(x, 0), 0, neg);
+            $select(flag, neg, x)
+        }
+        #[test]
+        fn $test_name() {
+            let m: $code = max!($code) - 4;
+            assert_eq!( $name(true, 0, m), 0);
+            assert_eq!( $name(false, 0, m), 0);
+            assert_eq!( $name(true, 1, m), m - 1);
+            assert_eq!( $name(false, 1, m), 1);
+            assert_eq!( $name(true, m - 1, m), 1);
+            assert_eq!( $name(true, 5, 7), 2);
+            assert_eq!( $name(false, 5, 7), 5);
+        }
+    }
+}
+ct_neg_mod_gen!(ct_neg_mod_if_u32,u32,ct_u32_eq,ct_select_u32;;test_ct_neg_mod_if_u32);
+ct_neg_mod_gen!(ct_neg_mod_if_u64,u64,ct_u64_eq,ct_select_u64;;test_ct_neg_mod_if_u64);
+
+/*
+ * High half of a 128x128 bit product, schoolbook over 64bit halves.
+ * None of the partial sums can overflow a u128.
+ */
+fn mulhi_u128(x: u128, y: u128) -> u128 {
+    let (x0, x1) = (x as u64 as u128, x >> 64);
+    let (y0, y1) = (y as u64 as u128, y >> 64);
+    let ll = x0 * y0;
+    let lh = x0 * y1;
+    let hl = x1 * y0;
+    let hh = x1 * y1;
+    let mid = (ll >> 64) + (lh as u64 as u128) + (hl as u64 as u128);
+    hh + (lh >> 64) + (hl >> 64) + (mid >> 64)
+}
+#[test]
+fn test_mulhi_u128() {
+    assert_eq!( mulhi_u128(u128::MAX, u128::MAX), u128::MAX - 1);
+    assert_eq!( mulhi_u128(u128::MAX, 2), 1);
+    assert_eq!( mulhi_u128(1 << 127, 4), 2);
+    assert_eq!( mulhi_u128(1 << 64, 1 << 64), 1);
+    assert_eq!( mulhi_u128(u64::MAX as u128, u64::MAX as u128), 0);
+}
+
+/*
+ * Barrett reduction with a full width reciprocal. With
+ * mu = floor((2^W - 1) / m) the estimate q = floor(x * mu / 2^W)
+ * is never more than one below floor(x / m), so x - q * m is below
+ * 2m and one masked subtraction finishes the job. The remainder can
+ * need one bit more than m, that bit is carried in the top half of
+ * the wide value and folded into the mask.
+ */
+macro_rules! ct_barrett_gen {
+    ($name:ident,$wide:ident,$code:ident,$mulhi:ident,$sbb:ident,$select:ident,$bits:expr
+    ;;$test_name:ident) => {
+        ///Barrett reduction, returns `x mod m`.
+        ///
+        ///`mu` must be `floor((2^BITS - 1) / m)` where `BITS` is the
+        ///width of `x`, it is a property of the public modulus and can
+        ///be computed once with `MAX / m`. `m` must be non-zero. The
+        ///final correction is a masked subtraction.
+        pub fn $name(x: $wide, m: $code, mu: $wide) -> $code {
+            let q = $mulhi(x, mu);
+            let r = x.wrapping_sub(q.wrapping_mul(m as $wide));
+            let r_lo = r as $code;
+            let r_hi = (r >> $bits) as $code;
+            let (diff, borrow) = $sbb(r_lo, m, 0);
+            let keep = r_hi | (borrow ^ 1);
+            let keep = unsafe{::core::mem::transmute::<u8,bool>(keep as u8)};
+            $select(keep, diff, r_lo)
+        }
+        #[test]
+        fn $test_name() {
+            const MAX: $code = max!($code);
+            const WMAX: $wide = max!($wide);
+            let moduli: [$code;6] = [1, 2, 7, 1 << ($bits - 1), MAX - 58, MAX];
+            let vals: [$wide;7] = [0, 1, 6, MAX as $wide, (MAX as $wide) * (MAX as $wide),
+                WMAX - 1, WMAX];
+            for &m in moduli.iter() {
+                let mu = WMAX / (m as $wide);
+                for &x in vals.iter() {
+                    assert_eq!( $name(x, m, mu), (x % (m as $wide)) as $code);
+                }
+            }
+        }
+    }
+}
+ct_barrett_gen!(ct_barrett_reduce_u64,u64,u32,ct_mulhi_u64,ct_sbb_u32,ct_select_u32,32;;
+    test_ct_barrett_reduce_u64);
+ct_barrett_gen!(ct_barrett_reduce_u128,u128,u64,mulhi_u128,ct_sbb_u64,ct_select_u64,64;;
+    test_ct_barrett_reduce_u128);
+
+/*
+ * Binary extended GCD with a fixed iteration count. u and v start as
+ * a and m with x1 * a = u and x2 * a = v (mod m). Each step makes u
+ * even, by swapping so u >= v and subtracting if u is odd, and then
+ * halves it. Each halving removes a bit from u or v so 2 * BITS steps
+ * are always enough, and every step does the same work no matter the
+ * values. v ends as the gcd and x2 as the inverse.
+ */
+macro_rules! ct_inverse_gen {
+    ($name:ident,$code:ident,$sub_mod:ident,$lt:ident,$eq:ident,$select:ident,$bits:expr
+    ;;$test_name:ident) => {
+        ///Modular inverse, returns `a^-1 mod m`.
+        ///
+        ///`m` must be odd and `a` must be below it. The boolean is true
+        ///if the inverse exists, if it is false `a` shares a factor with
+        ///`m` and the value is meaningless. The number of iterations
+        ///only depends on the width of the type.
+        pub fn $name(a: $code, m: $code) -> (bool, $code) {
+            let mut u = a;
+            let mut v = m;
+            let mut x1: $code = 1;
+            let mut x2: $code = 0;
+            let half = (m >> 1) + 1;
+            for _ in 0..(2 * $bits) {
+                let odd = unsafe{::core::mem::transmute::<u8,bool>((u & 1) as u8)};
+                let swap = odd & $lt(u, v);
+                let (nu, nv) = ($select(swap, v, u), $select(swap, u, v));
+                let (nx1, nx2) = ($select(swap, x2, x1), $select(swap, x1, x2));
+                u = $select(odd, nu.wrapping_sub(nv), nu);
+                x1 = $select(odd, $sub_mod(nx1, nx2, m), nx1);
+                v = nv;
+                x2 = nx2;
+                let x1_odd = unsafe{::core::mem::transmute::<u8,bool>((x1 & 1) as u8)};
+                u >>= 1;
+                x1 = (x1 >> 1) + $select(x1_odd, half, 0);
+            }
+            ($eq(v, 1), x2)
+        }
+        #[test]
+        fn $test_name() {
+            const MAX: $code = max!($code);
+            let moduli: [$code;4] = [3, 101, MAX - 4, MAX];
+            for &m in moduli.iter() {
+                let vals: [$code;5] = [1, 2, m / 2, m - 2, m - 1];
+                for &a in vals.iter() {
+                    let (ok, inv) = $name(a, m);
+                    let gcd_one = {
+                        let (mut p, mut q) = (a, m);
+                        while q != 0 { let t = p % q; p = q; q = t; }
+                        p == 1
+                    };
+                    assert_eq!( ok, gcd_one);
+                    if ok {
+                        assert_eq!( ((a as u128) * (inv as u128)) % (m as u128), 1);
+                    }
+                }
+            }
+            assert_eq!( $name(0, 7).0, false);
+            assert_eq!( $name(3, 9).0, false);
+            assert_eq!( $name(2, 9), (true, 5));
+        }
+    }
+}
+ct_inverse_gen!(ct_mod_inverse_u32,u32,ct_sub_mod_u32,ct_u32_lt,ct_u32_eq,ct_select_u32,32;;
+    test_ct_mod_inverse_u32);
+ct_inverse_gen!(ct_mod_inverse_u64,u64,ct_sub_mod_u64,ct_u64_lt,ct_u64_eq,ct_select_u64,64;;
+    test_ct_mod_inverse_u64);
+
+/*
+ * Trailing zero count that visits every bit. Zero counts as BITS.
+ */
+macro_rules! ct_ctz_gen {
+    ($name:ident,$code:ident,$bits:expr) => {
+        fn $name(x: $code) -> u32 {
+            let mut count: u32 = 0;
+            let mut still: u32 = 1;
+            for i in 0..$bits {
+                still &= (((x >> i) & 1) ^ 1) as u32;
+                count += still;
+            }
+            count
+        }
+    }
+}
+ct_ctz_gen!(ctz_u32,u32,32);
+ct_ctz_gen!(ctz_u64,u64,64);
+
+/*
+ * Stein's binary GCD with a fixed iteration count. The common power
+ * of two is pulled out first so that v can be made odd, then the same
+ * subtract and halve step as the inverse runs 2 * BITS times.
+ */
+macro_rules! ct_gcd_gen {
+    ($name:ident,$code:ident,$ctz:ident,$lt:ident,$eq:ident,$select:ident,$bits:expr
+    ;;$test_name:ident) => {
+        ///Greatest common divisor, `gcd(0, 0)` is `0`.
+        ///
+        ///The number of iterations only depends on the width of the
+        ///type.
+        pub fn $name(a: $code, b: $code) -> $code {
+            let both = a | b;
+            let k = $ctz(both);
+            let k = $select($eq(both, 0), 0, k as $code) as u32;
+            let a = a >> k;
+            let b = b >> k;
+            let b_even = unsafe{::core::mem::transmute::<u8,bool>(((b & 1) ^ 1) as u8)};
+            let mut u = $select(b_even, b, a);
+            let mut v = $select(b_even, a, b);
+            for _ in 0..(2 * $bits) {
+                let odd = unsafe{::core::mem::transmute::<u8,bool>((u & 1) as u8)};
+                let swap = odd & $lt(u, v);
+                let (nu, nv) = ($select(swap, v, u), $select(swap, u, v));
+                u = $select(odd, nu.wrapping_sub(nv), nu);
+                v = nv;
+                u >>= 1;
+            }
+            v << k
+        }
+        #[test]
+        fn $test_name() {
+            const MAX: $code = max!($code);
+            let vals: [$code;9] = [0, 1, 2, 6, 9, 12, 65537, MAX - 1, MAX];
+            for &a in vals.iter() {
+                for &b in vals.iter() {
+                    let (mut p, mut q) = (a, b);
+                    while q != 0 { let t = p % q; p = q; q = t; }
+                    assert_eq!( $name(a, b), p);
+                }
+            }
+            assert_eq!( $name(1 << ($bits - 1), 1 << ($bits - 2)), 1 << ($bits - 2));
+        }
+    }
+}
+ct_gcd_gen!(ct_gcd_u32,u32,ctz_u32,ct_u32_lt,ct_u32_eq,ct_select_u32,32;;
+    test_ct_gcd_u32);
+ct_gcd_gen!(ct_gcd_u64,u64,ctz_u64,ct_u64_lt,ct_u64_eq,ct_select_u64,64;;
+    test_ct_gcd_u64);
+
+///Serial number comparison from RFC 1982, returns if `a > b` in
+///`bits` bit serial number arithmetic.
+///
+///`a` is greater if `(a - b) mod 2^bits` is in `1..2^(bits - 1)`.
+///The distance of exactly half the space is undefined by the RFC and
+///compares as not greater either way. Only the low `bits` bits of `a`
+///and `b` are used, `bits` itself is public.
+///
+///#Panic:
+///
+///This function will panic if `bits` is not in `1..=64`.
+pub fn ct_serial_gt(a: u64, b: u64, bits: u32) -> bool {
+    if bits == 0 || bits > 64 {
+        panic!("Consistent Time: Attempted serial number compare with a bad width");
+    }
+    let mask = max!(u64) >> (64 - bits);
+    let half = 1u64 << (bits - 1);
+    let d = a.wrapping_sub(b) & mask;
+    !ct_u64_eq(d, 0) & ct_u64_lt(d, half)
+}
+#[test]
+fn test_ct_serial_gt() {
+    //examples from RFC 1982 section 5.2, SERIAL_BITS = 2
+    assert_eq!( ct_serial_gt(1, 0, 2), true);
+    assert_eq!( ct_serial_gt(2, 1, 2), true);
+    assert_eq!( ct_serial_gt(3, 2, 2), true);
+    assert_eq!( ct_serial_gt(0, 3, 2), true);
+    assert_eq!( ct_serial_gt(0, 1, 2), false);
+    assert_eq!( ct_serial_gt(2, 0, 2), false);
+    assert_eq!( ct_serial_gt(0, 2, 2), false);
+    assert_eq!( ct_serial_gt(1, 1, 2), false);
+    assert_eq!( ct_serial_gt(0, u32::MAX as u64, 32), true);
+    assert_eq!( ct_serial_gt(u32::MAX as u64, 0, 32), false);
+    assert_eq!( ct_serial_gt(0, u64::MAX, 64), true);
+    assert_eq!( ct_serial_gt(1 << 63, 5, 64), true);
+    assert_eq!( ct_serial_gt(1 << 63, 0, 64), false);
+    assert_eq!( ct_serial_gt(0x1_0000_0001, 0, 32), true);
+}
+#[test]
+#[should_panic]
+fn test_ct_serial_gt_panic() {
+    ct_serial_gt(0, 0, 65);
+}
+
+///Multiplication in GF(2^8) modulo the AES polynomial
+///`x^8 + x^4 + x^3 + x + 1`.
+///
+///Shift and add, for every bit of `b` the running multiple of `a` is
+///XOR'd in under a mask, and reduction by the polynomial is masked on
+///the bit shifted out. There are no log or antilog tables.
+pub fn ct_gf256_mul(a: u8, b: u8) -> u8 {
+    let mut a = a;
+    let mut res: u8 = 0;
+    for i in 0..8 {
+        res ^= a & ((b >> i) & 1).wrapping_neg();
+        let hi = a >> 7;
+        a = (a << 1) ^ (0x1B & hi.wrapping_neg());
+    }
+    res
+}
+#[test]
+fn test_ct_gf256_mul() {
+    //FIPS-197 section 4.2
+    assert_eq!( ct_gf256_mul(0x57, 0x83), 0xC1);
+    assert_eq!( ct_gf256_mul(0x57, 0x13), 0xFE);
+    assert_eq!( ct_gf256_mul(0x57, 0x02), 0xAE);
+    for a in 0..256 {
+        let a = a as u8;
+        assert_eq!( ct_gf256_mul(a, 1), a);
+        assert_eq!( ct_gf256_mul(a, 0), 0);
+        assert_eq!( ct_gf256_mul(a, 0x53), ct_gf256_mul(0x53, a));
+    }
+    assert_eq!( ct_gf256_mul(0x53, 0xCA), 0x01);
+}
+
+///Render a secret value as zero padded ASCII decimal digits.
+///
+///Exactly `N` digits are produced, most significant first, so a PIN
+///or OTP code keeps its leading zeros and its magnitude does not show
+///in the length. Each digit is a division by the constant 10, done as
+///a multiply by its reciprocal and a shift, which does not depend on
+///the value. If the value needs more than `N` digits the high ones
+///are dropped, `value mod 10^N` is written.
+pub fn ct_to_decimal_digits<const N: usize>(value: u64, out: &mut [u8; N]) {
+    let mut x = value;
+    for digit in out.iter_mut().rev() {
+        //floor(x / 10) for every u64 x
+        let q = (((x as u128) * 0xCCCCCCCCCCCCCCCD) >> 67) as u64;
+        *digit = b'0' + (x - q * 10) as u8;
+        x = q;
+    }
+}
+#[test]
+fn test_ct_to_decimal_digits() {
+    let mut out = [0u8;6];
+    ct_to_decimal_digits(42, &mut out);
+    assert_eq!( &out, b"000042");
+    ct_to_decimal_digits(123456, &mut out);
+    assert_eq!( &out, b"123456");
+    ct_to_decimal_digits(1234567, &mut out);
+    assert_eq!( &out, b"234567");
+    let mut out = [0u8;20];
+    ct_to_decimal_digits(u64::MAX, &mut out);
+    assert_eq!( &out, b"18446744073709551615");
+    let mut out = [0u8;0];
+    ct_to_decimal_digits(7, &mut out);
+    let mut state: u64 = 0x9E3779B97F4A7C15;
+    for _ in 0..1000 {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        let q = (((state as u128) * 0xCCCCCCCCCCCCCCCD) >> 67) as u64;
+        assert_eq!( q, state / 10);
+    }
+}
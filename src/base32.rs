@@ -0,0 +1,289 @@
+//Copyright 2016 William Cody Laeder
+//
+//Licensed under the Apache License, Version 2.0 (the "License");
+//you may not use this file except in compliance with the License.
+//you may obtain a copy of the License at
+//
+//  http://www.apache.org/licenses/LICENSE-2.0
+//
+//Unless required by applicable law or agreed to in writing, software
+//distrubuted under the License is distrubuted on as "AS IS" BASIS,
+//WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//See the License for the specific language governing permissions and
+//limitations under the License.
+
+
+//!Base32 encoding
+//!
+//!RFC 4648 Base32 for secret data such as TOTP seeds, in the standard
+//!and extended hex alphabets. As with the base64 codec characters are
+//!mapped with range masks and decoding errors are folded into one flag.
+//!The alphabet and padding choices are public and may branch.
+
+use super::{ct_u8_eq, ct_u8_lt, ct_select_u8, ct_select_usize, ct_usize_eq};
+
+///Base32 alphabets of RFC 4648.
+///
+///Decoding accepts letters in either case whatever the alphabet, the
+///case only picks what encoding writes.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Base32Alphabet {
+    ///`A-Z2-7`, section 6.
+    Standard,
+    ///`a-z2-7`.
+    StandardLower,
+    ///`0-9A-V`, section 7.
+    ExtendedHex,
+    ///`0-9a-v`.
+    ExtendedHexLower,
+}
+impl Base32Alphabet {
+    fn is_hex(self) -> bool {
+        self == Base32Alphabet::ExtendedHex || self == Base32Alphabet::ExtendedHexLower
+    }
+    fn letter_base(self) -> u8 {
+        if self == Base32Alphabet::StandardLower || self == Base32Alphabet::ExtendedHexLower {
+            b'a'
+        } else {
+            b'A'
+        }
+    }
+}
+
+/*
+ * Quintet of a character and if it was one. Letters are case folded
+ * with `| 0x20` before their range check.
+ */
+fn decode_char(c: u8, alphabet: Base32Alphabet) -> (u8, bool) {
+    let lower = c | 0x20;
+    if alphabet.is_hex() {
+        let digit = !ct_u8_lt(c, b'0') & ct_u8_lt(c, b'9' + 1);
+        let alpha = !ct_u8_lt(lower, b'a') & ct_u8_lt(lower, b'v' + 1);
+        let val = ct_select_u8(digit, c.wrapping_sub(b'0'), 0)
+            | ct_select_u8(alpha, lower.wrapping_sub(b'a' - 10), 0);
+        (val, digit | alpha)
+    } else {
+        let alpha = !ct_u8_lt(lower, b'a') & ct_u8_lt(lower, b'z' + 1);
+        let digit = !ct_u8_lt(c, b'2') & ct_u8_lt(c, b'7' + 1);
+        let val = ct_select_u8(alpha, lower.wrapping_sub(b'a'), 0)
+            | ct_select_u8(digit, c.wrapping_sub(b'2' - 26), 0);
+        (val, digit | alpha)
+    }
+}
+/*
+ * Character of a quintet, the first range plus an offset to the
+ * second range added under a mask.
+ */
+fn encode_char(n: u8, alphabet: Base32Alphabet) -> u8 {
+    let letter = alphabet.letter_base();
+    if alphabet.is_hex() {
+        let second = ct_u8_lt(9, n);
+        n.wrapping_add(ct_select_u8(second, letter.wrapping_sub(10), b'0'))
+    } else {
+        let second = ct_u8_lt(25, n);
+        n.wrapping_add(ct_select_u8(second, b'2'.wrapping_sub(26), letter))
+    }
+}
+
+/*
+ * Characters carrying data in a final group for each number of
+ * padding characters, and the bytes they make. Any other amount of
+ * padding is invalid.
+ */
+const PADDING: [(usize, usize);5] = [(0, 5), (1, 4), (3, 3), (4, 2), (6, 1)];
+
+fn encode_group(group: &mut [u8], chunk: &[u8], alphabet: Base32Alphabet) {
+    let mut bytes = [0u8;5];
+    bytes[..chunk.len()].copy_from_slice(chunk);
+    let mut bits: u64 = 0;
+    for b in bytes.iter() {
+        bits = (bits << 8) | (*b as u64);
+    }
+    for (i, c) in group.iter_mut().enumerate() {
+        *c = encode_char(((bits >> (35 - 5 * i)) & 0x1F) as u8, alphabet);
+    }
+    //padding only depends on the (public) length
+    let used = (chunk.len() * 8).div_ceil(5);
+    for c in group[used..].iter_mut() {
+        *c = b'=';
+    }
+}
+/*
+ * Decode a group of 8 characters into 5 bytes. Only the last group may
+ * have padding, it must be a suffix of one of the allowed lengths and
+ * the unused bits of the last data character must be zero. Returns if
+ * the group was valid and how many bytes it holds.
+ */
+fn decode_group(chunk: &mut [u8], group: &[u8], alphabet: Base32Alphabet, last: bool) -> (bool, usize) {
+    let mut bits: u64 = 0;
+    let mut valid = true;
+    let mut pads: usize = 0;
+    let mut in_pad = false;
+    let mut vals = [0u8;8];
+    for (i, c) in group.iter().enumerate() {
+        let (val, ok) = decode_char(*c, alphabet);
+        let is_pad = last & ct_u8_eq(*c, b'=');
+        //once padding starts it has to run to the end
+        valid &= !(in_pad & !is_pad);
+        in_pad |= is_pad;
+        valid &= ok | is_pad;
+        pads += is_pad as usize;
+        vals[i] = val;
+        bits = (bits << 5) | (val as u64);
+    }
+    let mut allowed = false;
+    let mut len: usize = 0;
+    for &(p, bytes) in PADDING.iter() {
+        let hit = ct_usize_eq(pads, p);
+        allowed |= hit;
+        len = ct_select_usize(hit, bytes, len);
+        if p > 0 {
+            let unused = ((8 - p) * 5 - bytes * 8) as u32;
+            let stray = vals[7 - p] & ((1u8 << unused) - 1);
+            valid &= !(hit & !ct_u8_eq(stray, 0));
+        }
+    }
+    for (i, b) in chunk.iter_mut().enumerate() {
+        *b = (bits >> (32 - 8 * i)) as u8;
+    }
+    (valid & allowed, len)
+}
+
+///Length of the base32 encoding of `len` bytes.
+pub fn ct_base32_encoded_len(len: usize, pad: bool) -> usize {
+    if pad {
+        len.div_ceil(5) * 8
+    } else {
+        (len * 8).div_ceil(5)
+    }
+}
+///Encode bytes as base32.
+///
+///#Panic:
+///
+///This function will panic if `out` is not `ct_base32_encoded_len`
+///long.
+pub fn ct_base32_encode(out: &mut [u8], data: &[u8], alphabet: Base32Alphabet, pad: bool) {
+    if out.len() != ct_base32_encoded_len(data.len(), pad) {
+        panic!("Consistent Time: Attempted base32 encode into a mis-sized output");
+    }
+    let full = data.len() / 5;
+    for (group, chunk) in out.chunks_mut(8).zip(data.chunks(5)).take(full) {
+        encode_group(group, chunk, alphabet);
+    }
+    let tail = &data[full * 5..];
+    if !tail.is_empty() {
+        let mut group = [0u8;8];
+        encode_group(&mut group, tail, alphabet);
+        let rest = &mut out[full * 8..];
+        let len = rest.len();
+        rest.copy_from_slice(&group[..len]);
+    }
+}
+///Decode base32.
+///
+///Returns if the input was valid and the number of bytes decoded.
+///Every character is decoded, if the input was invalid the contents
+///of `out` are meaningless. With `pad` the input must be a multiple
+///of 8 long and padding is strictly checked, without it any `=` is
+///invalid. Either way the unused bits of the last character must be
+///zero.
+///
+///#Panic:
+///
+///This function will panic if `out` is shorter than `5 * input.len() / 8`
+///rounded up to a whole group when padded.
+pub fn ct_base32_decode(out: &mut [u8], input: &[u8], alphabet: Base32Alphabet, pad: bool) -> (bool, usize) {
+    let len = input.len();
+    let full = len / 8;
+    if pad && !len.is_multiple_of(8) {
+        return (false, 0);
+    }
+    let max = if pad { full * 5 } else { (len * 5) / 8 };
+    if out.len() < max {
+        panic!("Consistent Time: Attempted base32 decode into a mis-sized output");
+    }
+    let mut valid = true;
+    let mut decoded = full * 5;
+    for (g, (chunk, group)) in out.chunks_mut(5).zip(input.chunks(8)).take(full).enumerate() {
+        let last = pad & (g + 1 == full);
+        let (ok, bytes) = decode_group(chunk, group, alphabet, last);
+        valid &= ok;
+        if last {
+            decoded = decoded - 5 + bytes;
+        }
+    }
+    let tail = &input[full * 8..];
+    if !tail.is_empty() {
+        let mut group = [b'='; 8];
+        group[..tail.len()].copy_from_slice(tail);
+        for c in tail.iter() {
+            valid &= !ct_u8_eq(*c, b'=');
+        }
+        let mut chunk = [0u8;5];
+        let (ok, bytes) = decode_group(&mut chunk, &group, alphabet, true);
+        valid &= ok;
+        let rest = &mut out[full * 5..max];
+        let n = rest.len();
+        rest.copy_from_slice(&chunk[..n]);
+        valid &= ct_usize_eq(bytes, n);
+        decoded += n;
+    }
+    (valid, decoded)
+}
+#[test]
+fn test_ct_base32() {
+    //RFC 4648 section 10
+    let vectors: [(&[u8], &[u8], &[u8]);7] = [
+        (b"", b"", b""),
+        (b"f", b"MY======", b"CO======"),
+        (b"fo", b"MZXQ====", b"CPNG===="),
+        (b"foo", b"MZXW6===", b"CPNMU==="),
+        (b"foob", b"MZXW6YQ=", b"CPNMUOG="),
+        (b"fooba", b"MZXW6YTB", b"CPNMUOJ1"),
+        (b"foobar", b"MZXW6YTBOI======", b"CPNMUOJ1E8======"),
+    ];
+    for &(data, std, hex) in vectors.iter() {
+        for &(alphabet, want) in [(Base32Alphabet::Standard, std),
+            (Base32Alphabet::ExtendedHex, hex)].iter() {
+            let mut enc = [0u8;16];
+            let enc = &mut enc[..ct_base32_encoded_len(data.len(), true)];
+            ct_base32_encode(enc, data, alphabet, true);
+            assert_eq!( &enc[..], want);
+            let mut dec = [0u8;10];
+            let (ok, len) = ct_base32_decode(&mut dec, want, alphabet, true);
+            assert_eq!( (ok, &dec[..len]), (true, data));
+            //unpadded
+            let trimmed = ct_base32_encoded_len(data.len(), false);
+            let mut enc = [0u8;16];
+            ct_base32_encode(&mut enc[..trimmed], data, alphabet, false);
+            assert_eq!( &enc[..trimmed], &want[..trimmed]);
+            let (ok, len) = ct_base32_decode(&mut dec, &want[..trimmed], alphabet, false);
+            assert_eq!( (ok, &dec[..len]), (true, data));
+        }
+    }
+    let mut enc = [0u8;8];
+    ct_base32_encode(&mut enc, b"fooba", Base32Alphabet::StandardLower, true);
+    assert_eq!( &enc, b"mzxw6ytb");
+    ct_base32_encode(&mut enc, b"fooba", Base32Alphabet::ExtendedHexLower, true);
+    assert_eq!( &enc, b"cpnmuoj1");
+    let mut dec = [0u8;5];
+    assert_eq!( ct_base32_decode(&mut dec, b"mzXW6ytb", Base32Alphabet::Standard, true),
+        (true, 5));
+    assert_eq!( &dec, b"fooba");
+    let bad: [&[u8];7] = [b"MY=====", b"M=======", b"MY=A====", b"MZ======",
+        b"MZXW6YT=", b"MZXW6Y1B", b"MZXW6YW="];
+    for b in bad.iter() {
+        assert_eq!( ct_base32_decode(&mut dec, b, Base32Alphabet::Standard, true).0, false);
+    }
+    let bad: [&[u8];4] = [b"MY=", b"M", b"MZX", b"MZ"];
+    for b in bad.iter() {
+        assert_eq!( ct_base32_decode(&mut dec, b, Base32Alphabet::Standard, false).0, false);
+    }
+}
+#[test]
+#[should_panic]
+fn test_ct_base32_decode_panic() {
+    let mut dec = [0u8;4];
+    ct_base32_decode(&mut dec, b"MZXW6YTB", Base32Alphabet::Standard, true);
+}
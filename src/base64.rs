@@ -0,0 +1,373 @@
+//Copyright 2016 William Cody Laeder
+//
+//Licensed under the Apache License, Version 2.0 (the "License");
+//you may not use this file except in compliance with the License.
+//you may obtain a copy of the License at
+//
+//  http://www.apache.org/licenses/LICENSE-2.0
+//
+//Unless required by applicable law or agreed to in writing, software
+//distrubuted under the License is distrubuted on as "AS IS" BASIS,
+//WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//See the License for the specific language governing permissions and
+//limitations under the License.
+
+
+//!Base64 encoding
+//!
+//!Base64 and base64url codecs for secret data, private keys and
+//!tokens. Characters are mapped with range masks instead of an
+//!alphabet table and every decoding error, padding included, is
+//!folded into one flag that is returned after the whole input was
+//!read. Only the lengths, which are public, ever decide a branch.
+
+use super::{ct_u8_eq, ct_u8_lt, ct_select_u8, ct_u8_slice_eq};
+
+/*
+ * Sextet of a character of the alphabet, 'A'..'Z', 'a'..'z', '0'..'9'
+ * and the two alphabet specific ones, and if it was one.
+ */
+fn decode_char(c: u8, c62: u8, c63: u8) -> (u8, bool) {
+    let upper = !ct_u8_lt(c, b'A') & ct_u8_lt(c, b'Z' + 1);
+    let lower = !ct_u8_lt(c, b'a') & ct_u8_lt(c, b'z' + 1);
+    let digit = !ct_u8_lt(c, b'0') & ct_u8_lt(c, b'9' + 1);
+    let is_62 = ct_u8_eq(c, c62);
+    let is_63 = ct_u8_eq(c, c63);
+    let val = ct_select_u8(upper, c.wrapping_sub(b'A'), 0)
+        | ct_select_u8(lower, c.wrapping_sub(b'a' - 26), 0)
+        | ct_select_u8(digit, c.wrapping_add(52 - b'0'), 0)
+        | ct_select_u8(is_62, 62, 0)
+        | ct_select_u8(is_63, 63, 0);
+    (val, upper | lower | digit | is_62 | is_63)
+}
+/*
+ * Character of a sextet. 'A' + n is right for 0..26, from 26 on 6 is
+ * added to reach 'a' + n - 26 and from 52 on 75 is taken off to land
+ * on '0' + n - 52. 62 and 63 are swapped in last.
+ */
+fn encode_char(n: u8, c62: u8, c63: u8) -> u8 {
+    let c = n + b'A';
+    let c = c + ct_select_u8(ct_u8_lt(25, n), 6, 0);
+    let c = c.wrapping_sub(ct_select_u8(ct_u8_lt(51, n), 75, 0));
+    let c = ct_select_u8(ct_u8_eq(n, 62), c62, c);
+    ct_select_u8(ct_u8_eq(n, 63), c63, c)
+}
+
+fn encode_quad(quad: &mut [u8], chunk: &[u8], c62: u8, c63: u8) {
+    let len = chunk.len();
+    let b0 = chunk[0];
+    let b1 = if len > 1 { chunk[1] } else { 0 };
+    let b2 = if len > 2 { chunk[2] } else { 0 };
+    quad[0] = encode_char(b0 >> 2, c62, c63);
+    quad[1] = encode_char(((b0 << 4) | (b1 >> 4)) & 0x3F, c62, c63);
+    quad[2] = encode_char(((b1 << 2) | (b2 >> 6)) & 0x3F, c62, c63);
+    quad[3] = encode_char(b2 & 0x3F, c62, c63);
+    //padding only depends on the (public) length
+    if len < 3 {
+        quad[3] = b'=';
+    }
+    if len < 2 {
+        quad[2] = b'=';
+    }
+}
+fn encode_with(out: &mut [u8], data: &[u8], c62: u8, c63: u8, pad: bool) {
+    let want = if pad {
+        ct_base64_encoded_len(data.len())
+    } else {
+        ct_base64_encoded_len_unpadded(data.len())
+    };
+    if out.len() != want {
+        panic!("Consistent Time: Attempted base64 encode into a mis-sized output");
+    }
+    let full = data.len() / 3;
+    for (quad, chunk) in out.chunks_mut(4).zip(data.chunks(3)).take(full) {
+        encode_quad(quad, chunk, c62, c63);
+    }
+    let tail = &data[full * 3..];
+    if !tail.is_empty() {
+        let mut quad = [0u8;4];
+        encode_quad(&mut quad, tail, c62, c63);
+        let rest = &mut out[full * 4..];
+        let len = rest.len();
+        rest.copy_from_slice(&quad[..len]);
+    }
+}
+
+/*
+ * Every quad is decoded in full. '=' is only valid as the last one or
+ * two characters of the last quad, and then the bits of the final
+ * character that fall past the data must be zero so every input has
+ * a single encoding. Returns if the quad was valid and the number of
+ * padding characters.
+ */
+fn decode_quad(chunk: &mut [u8], quad: &[u8], c62: u8, c63: u8, last: bool) -> (bool, usize) {
+    let (v0, ok0) = decode_char(quad[0], c62, c63);
+    let (v1, ok1) = decode_char(quad[1], c62, c63);
+    let (v2, ok2) = decode_char(quad[2], c62, c63);
+    let (v3, ok3) = decode_char(quad[3], c62, c63);
+    let pad2 = last & ct_u8_eq(quad[2], b'=');
+    let pad3 = last & ct_u8_eq(quad[3], b'=');
+    let mut valid = ok0 & ok1 & (ok2 | (pad2 & pad3)) & (ok3 | pad3);
+    //unused bits must be zero
+    valid &= !(pad2 & !ct_u8_eq(v1 & 0x0F, 0));
+    valid &= !(pad3 & !pad2 & !ct_u8_eq(v2 & 0x03, 0));
+    chunk[0] = (v0 << 2) | (v1 >> 4);
+    chunk[1] = (v1 << 4) | (v2 >> 2);
+    chunk[2] = (v2 << 6) | v3;
+    (valid, (pad2 as usize) + (pad3 as usize))
+}
+fn decode_with(out: &mut [u8], input: &[u8], c62: u8, c63: u8) -> (bool, usize) {
+    let len = input.len();
+    if !len.is_multiple_of(4) {
+        return (false, 0);
+    }
+    let max = (len / 4) * 3;
+    if out.len() < max {
+        panic!("Consistent Time: Attempted base64 decode into a mis-sized output");
+    }
+    let quads = len / 4;
+    let mut valid = true;
+    let mut pads: usize = 0;
+    for (q, (chunk, quad)) in out.chunks_mut(3).zip(input.chunks(4)).enumerate() {
+        let (ok, p) = decode_quad(chunk, quad, c62, c63, q + 1 == quads);
+        valid &= ok;
+        pads = p;
+    }
+    (valid, max - pads)
+}
+/*
+ * Unpadded input, the last partial quad is padded out on the stack
+ * and any '=' that was really in the input is rejected.
+ */
+fn decode_unpadded_with(out: &mut [u8], input: &[u8], c62: u8, c63: u8) -> (bool, usize) {
+    let len = input.len();
+    if len % 4 == 1 {
+        return (false, 0);
+    }
+    let decoded = (len * 3) / 4;
+    if out.len() < decoded {
+        panic!("Consistent Time: Attempted base64 decode into a mis-sized output");
+    }
+    let full = len / 4;
+    let mut valid = true;
+    for (chunk, quad) in out.chunks_mut(3).zip(input.chunks(4)).take(full) {
+        valid &= decode_quad(chunk, quad, c62, c63, false).0;
+    }
+    let tail = &input[full * 4..];
+    if !tail.is_empty() {
+        let mut quad = [b'='; 4];
+        quad[..tail.len()].copy_from_slice(tail);
+        for c in tail.iter() {
+            valid &= !ct_u8_eq(*c, b'=');
+        }
+        let mut chunk = [0u8;3];
+        valid &= decode_quad(&mut chunk, &quad, c62, c63, true).0;
+        let rest = &mut out[full * 3..decoded];
+        let n = rest.len();
+        rest.copy_from_slice(&chunk[..n]);
+    }
+    (valid, decoded)
+}
+
+///Length of the padded base64 encoding of `len` bytes.
+pub fn ct_base64_encoded_len(len: usize) -> usize {
+    len.div_ceil(3) * 4
+}
+///Length of the unpadded base64 encoding of `len` bytes.
+pub fn ct_base64_encoded_len_unpadded(len: usize) -> usize {
+    (len * 4).div_ceil(3)
+}
+///Encode bytes as padded base64, standard alphabet.
+///
+///#Panic:
+///
+///This function will panic if `out` is not `ct_base64_encoded_len`
+///long.
+pub fn ct_base64_encode(out: &mut [u8], data: &[u8]) {
+    encode_with(out, data, b'+', b'/', true);
+}
+///Decode padded base64, standard alphabet.
+///
+///Returns if the input was valid and the number of bytes decoded.
+///Every character is decoded and `3 * input.len() / 4` bytes are
+///always written, if the input was invalid the contents of `out` are
+///meaningless. Padding is strictly checked and the unused bits before
+///it must be zero. An input that is not a multiple of 4 long is
+///invalid.
+///
+///#Panic:
+///
+///This function will panic if `out` is shorter than
+///`3 * input.len() / 4`.
+pub fn ct_base64_decode(out: &mut [u8], input: &[u8]) -> (bool, usize) {
+    decode_with(out, input, b'+', b'/')
+}
+#[test]
+fn test_ct_base64() {
+    //RFC 4648 section 10
+    let vectors: [(&[u8], &[u8]);7] = [
+        (b"", b""),
+        (b"f", b"Zg=="),
+        (b"fo", b"Zm8="),
+        (b"foo", b"Zm9v"),
+        (b"foob", b"Zm9vYg=="),
+        (b"fooba", b"Zm9vYmE="),
+        (b"foobar", b"Zm9vYmFy"),
+    ];
+    for &(data, b64) in vectors.iter() {
+        let mut enc = [0u8;8];
+        let enc = &mut enc[..ct_base64_encoded_len(data.len())];
+        ct_base64_encode(enc, data);
+        assert_eq!( &enc[..], b64);
+        let mut dec = [0u8;6];
+        let (ok, len) = ct_base64_decode(&mut dec, b64);
+        assert_eq!( ok, true);
+        assert_eq!( &dec[..len], data);
+    }
+    let mut enc = [0u8;4];
+    ct_base64_encode(&mut enc, &[0xFB, 0xFF, 0xBF]);
+    assert_eq!( &enc, b"+/+/");
+    for c in 0..256 {
+        let c = c as u8;
+        let (val, ok) = decode_char(c, b'+', b'/');
+        assert_eq!( ok, (c as char).is_ascii_alphanumeric() || c == b'+' || c == b'/');
+        if ok {
+            assert_eq!( encode_char(val, b'+', b'/'), c);
+        }
+    }
+    let bad: [&[u8];8] = [b"Zg=", b"Z===", b"Zh==", b"Zm9=", b"=m9v", b"Zm=v",
+        b"Zg==Zg==", b"Zm9v.g=="];
+    let mut dec = [0u8;6];
+    for b in bad.iter() {
+        assert_eq!( ct_base64_decode(&mut dec, b).0, false);
+    }
+}
+#[test]
+#[should_panic]
+fn test_ct_base64_decode_panic() {
+    let mut dec = [0u8;2];
+    ct_base64_decode(&mut dec, b"Zm9v");
+}
+
+///Encode bytes as padded base64url, the URL and filename safe
+///alphabet with `-` and `_`.
+///
+///#Panic:
+///
+///This function will panic if `out` is not `ct_base64_encoded_len`
+///long.
+pub fn ct_base64url_encode(out: &mut [u8], data: &[u8]) {
+    encode_with(out, data, b'-', b'_', true);
+}
+///Encode bytes as unpadded base64url, as used by JWT.
+///
+///#Panic:
+///
+///This function will panic if `out` is not
+///`ct_base64_encoded_len_unpadded` long.
+pub fn ct_base64url_encode_unpadded(out: &mut [u8], data: &[u8]) {
+    encode_with(out, data, b'-', b'_', false);
+}
+///Decode padded base64url.
+///
+///Behaves as `ct_base64_decode` with the URL safe alphabet.
+///
+///#Panic:
+///
+///This function will panic if `out` is shorter than
+///`3 * input.len() / 4`.
+pub fn ct_base64url_decode(out: &mut [u8], input: &[u8]) -> (bool, usize) {
+    decode_with(out, input, b'-', b'_')
+}
+///Decode unpadded base64url.
+///
+///Returns if the input was valid and the number of bytes decoded,
+///which is always `3 * input.len() / 4`. Any `=` is invalid, as is an
+///input one past a multiple of 4 long. The unused bits of the last
+///character must be zero.
+///
+///#Panic:
+///
+///This function will panic if `out` is shorter than
+///`3 * input.len() / 4`.
+pub fn ct_base64url_decode_unpadded(out: &mut [u8], input: &[u8]) -> (bool, usize) {
+    decode_unpadded_with(out, input, b'-', b'_')
+}
+#[test]
+fn test_ct_base64url() {
+    let vectors: [(&[u8], &[u8], &[u8]);5] = [
+        (b"", b"", b""),
+        (b"f", b"Zg==", b"Zg"),
+        (b"fo", b"Zm8=", b"Zm8"),
+        (b"foo", b"Zm9v", b"Zm9v"),
+        (b"\xFB\xFF\xBF\xFB", b"-_-_-w==", b"-_-_-w"),
+    ];
+    for &(data, padded, unpadded) in vectors.iter() {
+        let mut enc = [0u8;8];
+        let enc = &mut enc[..ct_base64_encoded_len(data.len())];
+        ct_base64url_encode(enc, data);
+        assert_eq!( &enc[..], padded);
+        let mut enc = [0u8;8];
+        let enc = &mut enc[..ct_base64_encoded_len_unpadded(data.len())];
+        ct_base64url_encode_unpadded(enc, data);
+        assert_eq!( &enc[..], unpadded);
+        let mut dec = [0u8;6];
+        let (ok, len) = ct_base64url_decode(&mut dec, padded);
+        assert_eq!( (ok, &dec[..len]), (true, data));
+        let (ok, len) = ct_base64url_decode_unpadded(&mut dec, unpadded);
+        assert_eq!( (ok, &dec[..len]), (true, data));
+    }
+    let mut dec = [0u8;6];
+    let bad: [&[u8];6] = [b"Zg==", b"Zg=", b"Z", b"Zh", b"Zm9", b"+/+/"];
+    for b in bad.iter() {
+        assert_eq!( ct_base64url_decode_unpadded(&mut dec, b).0, false);
+    }
+    assert_eq!( ct_base64url_decode(&mut dec, b"+/+/").0, false);
+    assert_eq!( ct_base64_decode(&mut dec, b"-_-_").0, false);
+}
+
+///Check a presented JWS/JWT signature against a computed MAC.
+///
+///`presented` is the unpadded base64url signature segment of the
+///token and `computed` the raw MAC the server calculated, e.g. an
+///HMAC-SHA256 tag. The signature is decoded with
+///`ct_base64url_decode_unpadded` into a stack buffer and compared in
+///full, a decoding error and a wrong MAC give the same `false`. A
+///presented signature of the wrong (public) length is rejected
+///straight away.
+///
+///#Panic:
+///
+///This function will panic if `computed` is longer than 64 bytes.
+pub fn ct_verify_jwt_signature(computed: &[u8], presented: &[u8]) -> bool {
+    let len = computed.len();
+    if len > 64 {
+        panic!("Consistent Time: Attempted to verify a MAC longer than 64 bytes");
+    }
+    if presented.len() != ct_base64_encoded_len_unpadded(len) {
+        return false;
+    }
+    let mut raw = [0u8;64];
+    let (valid, _) = ct_base64url_decode_unpadded(&mut raw[..len], presented);
+    valid & ct_u8_slice_eq(computed, &raw[..len])
+}
+#[test]
+fn test_ct_verify_jwt_signature() {
+    //RFC 7515 appendix A.1
+    let sig = b"dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+    let mac: [u8;32] = [116, 24, 223, 180, 151, 153, 224, 37, 79, 250, 96, 125,
+        216, 173, 187, 186, 22, 212, 37, 77, 105, 214, 191, 240, 91, 88, 5, 88,
+        83, 132, 141, 121];
+    assert_eq!( ct_verify_jwt_signature(&mac, sig), true);
+    let mut wrong = mac;
+    wrong[31] ^= 1;
+    assert_eq!( ct_verify_jwt_signature(&wrong, sig), false);
+    assert_eq!( ct_verify_jwt_signature(&mac, &sig[..42]), false);
+    let mut bad = *sig;
+    bad[0] = b'+';
+    assert_eq!( ct_verify_jwt_signature(&mac, &bad), false);
+    //the last character carries 2 unused bits that must be zero
+    let mut bad = *sig;
+    bad[42] = b'l';
+    assert_eq!( ct_verify_jwt_signature(&mac, &bad), false);
+}
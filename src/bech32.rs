@@ -0,0 +1,274 @@
+//Copyright 2016 William Cody Laeder
+//
+//Licensed under the Apache License, Version 2.0 (the "License");
+//you may not use this file except in compliance with the License.
+//you may obtain a copy of the License at
+//
+//  http://www.apache.org/licenses/LICENSE-2.0
+//
+//Unless required by applicable law or agreed to in writing, software
+//distrubuted under the License is distrubuted on as "AS IS" BASIS,
+//WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//See the License for the specific language governing permissions and
+//limitations under the License.
+
+
+//!Bech32 data decoding
+//!
+//!The data part of BIP 173 bech32 and BIP 350 bech32m strings, for key
+//!material. The charset has no range structure so every character is
+//!compared against all 32 entries, the checksum is the usual BCH
+//!polymod with its generator applied under masks, and every error is
+//!folded into one flag. The human readable part is public.
+
+use super::{ct_u8_eq, ct_u8_lt, ct_u32_eq, ct_select_u8};
+
+const CHARSET: &[u8;32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const GENERATOR: [u32;5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+///Number of checksum characters at the end of the data part.
+pub const CHECKSUM_LEN: usize = 6;
+
+///Checksum constant, bech32 for segwit v0 and bech32m for later.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Bech32Variant {
+    ///BIP 173, the checksum constant is `1`.
+    Bech32,
+    ///BIP 350, the checksum constant is `0x2bc830a3`.
+    Bech32m,
+}
+impl Bech32Variant {
+    fn constant(self) -> u32 {
+        match self {
+            Bech32Variant::Bech32 => 1,
+            Bech32Variant::Bech32m => 0x2bc830a3,
+        }
+    }
+}
+
+fn polymod_step(chk: u32, val: u8) -> u32 {
+    let top = chk >> 25;
+    let mut chk = ((chk & 0x1ffffff) << 5) ^ (val as u32);
+    for (i, g) in GENERATOR.iter().enumerate() {
+        chk ^= g & ((top >> i) & 1).wrapping_neg();
+    }
+    chk
+}
+/*
+ * Polymod state after the expanded human readable part, which is
+ * public. It is lowercased first as the checksum is defined over the
+ * lowercase string.
+ */
+fn polymod_hrp(hrp: &[u8]) -> u32 {
+    let mut chk: u32 = 1;
+    for c in hrp.iter() {
+        chk = polymod_step(chk, c.to_ascii_lowercase() >> 5);
+    }
+    chk = polymod_step(chk, 0);
+    for c in hrp.iter() {
+        chk = polymod_step(chk, c.to_ascii_lowercase() & 31);
+    }
+    chk
+}
+
+/*
+ * Tracks the case of the string, bech32 may be upper or lower case
+ * but never both.
+ */
+struct CaseState {
+    upper: bool,
+    lower: bool,
+}
+impl CaseState {
+    fn new() -> CaseState {
+        CaseState{ upper: false, lower: false }
+    }
+    fn is_mixed(&self) -> bool {
+        self.upper & self.lower
+    }
+}
+/*
+ * Value of a data character and if it was one. Uppercase letters are
+ * folded, then the character is compared against the whole charset.
+ */
+fn decode_char(c: u8, case: &mut CaseState) -> (u8, bool) {
+    let is_upper = !ct_u8_lt(c, b'A') & ct_u8_lt(c, b'Z' + 1);
+    let is_lower = !ct_u8_lt(c, b'a') & ct_u8_lt(c, b'z' + 1);
+    case.upper |= is_upper;
+    case.lower |= is_lower;
+    let folded = c | ct_select_u8(is_upper, 0x20, 0);
+    let mut val: u8 = 0;
+    let mut found = false;
+    for (i, entry) in CHARSET.iter().enumerate() {
+        let hit = ct_u8_eq(folded, *entry);
+        val |= ct_select_u8(hit, i as u8, 0);
+        found |= hit;
+    }
+    (val, found)
+}
+
+///Convert bech32 data characters to 5 bit values.
+///
+///Returns if every character is in the charset and the string is not
+///mixed case. Every character is converted either way.
+///
+///#Panic:
+///
+///This function will panic if the slices are not equal length.
+pub fn ct_bech32_to_u5(out: &mut [u8], data: &[u8]) -> bool {
+    if out.len() != data.len() {
+        panic!("Consistent Time: Attempted bech32 conversion between non-equal lens");
+    }
+    let mut case = CaseState::new();
+    let mut valid = true;
+    for (o, c) in out.iter_mut().zip(data.iter()) {
+        let (val, ok) = decode_char(*c, &mut case);
+        *o = val;
+        valid &= ok;
+    }
+    valid & !case.is_mixed()
+}
+///Verify the checksum over a human readable part and 5 bit values.
+///
+///`data` includes the checksum values at the end.
+pub fn ct_bech32_verify_checksum(hrp: &[u8], data: &[u8], variant: Bech32Variant) -> bool {
+    let mut chk = polymod_hrp(hrp);
+    for v in data.iter() {
+        chk = polymod_step(chk, *v);
+    }
+    ct_u32_eq(chk, variant.constant())
+}
+
+/*
+ * Regrouping of 5 bit values into bytes, bits are shifted into an
+ * accumulator and a byte is written after every 8. The number of
+ * bytes only depends on the (public) input length.
+ */
+struct Regroup {
+    acc: u32,
+    bits: usize,
+    pos: usize,
+}
+impl Regroup {
+    fn push(&mut self, out: &mut [u8], val: u8) {
+        self.acc = ((self.acc << 5) | (val as u32)) & 0xFFF;
+        self.bits += 5;
+        if self.bits >= 8 {
+            self.bits -= 8;
+            out[self.pos] = (self.acc >> self.bits) as u8;
+            self.pos += 1;
+        }
+    }
+    //leftover bits are padding and must be zero
+    fn finish(&self) -> bool {
+        let mask = (1u32 << self.bits) - 1;
+        ct_u32_eq(self.acc & mask, 0)
+    }
+}
+
+///Regroup 5 bit values into bytes, as `convertbits(data, 5, 8, False)`
+///of BIP 173.
+///
+///Returns if the padding bits at the end are zero. `out` receives
+///`5 * data.len() / 8` bytes.
+///
+///#Panic:
+///
+///This function will panic if `out` is the wrong length, or if there
+///would be 5 or more bits of padding, which is never valid.
+pub fn ct_bech32_convert_bits(out: &mut [u8], data: &[u8]) -> bool {
+    if out.len() != (data.len() * 5) / 8 || (data.len() * 5) % 8 >= 5 {
+        panic!("Consistent Time: Attempted bech32 regroup into a mis-sized output");
+    }
+    let mut regroup = Regroup{ acc: 0, bits: 0, pos: 0 };
+    for v in data.iter() {
+        regroup.push(out, *v & 31);
+    }
+    regroup.finish()
+}
+
+///Decode the data part of a bech32 string into bytes in one pass.
+///
+///`data` is everything after the `1` separator, checksum included.
+///Every character is converted, fed to the checksum and, for all but
+///the checksum, regrouped into `out`. Returns if the characters, the
+///case, the padding bits and the checksum were all valid, and the
+///number of bytes written, `5 * (data.len() - 6) / 8`.
+///
+///A string whose payload would end with 5 or more padding bits, or
+///that is too short to hold a checksum, is invalid.
+///
+///#Panic:
+///
+///This function will panic if `out` is too short.
+pub fn ct_bech32_decode(out: &mut [u8], hrp: &[u8], data: &[u8], variant: Bech32Variant) -> (bool, usize) {
+    if data.len() < CHECKSUM_LEN {
+        return (false, 0);
+    }
+    let payload = data.len() - CHECKSUM_LEN;
+    let bytes = (payload * 5) / 8;
+    if (payload * 5) % 8 >= 5 {
+        return (false, 0);
+    }
+    if out.len() < bytes {
+        panic!("Consistent Time: Attempted bech32 decode into a mis-sized output");
+    }
+    let mut case = CaseState::new();
+    let mut valid = true;
+    let mut chk = polymod_hrp(hrp);
+    let mut regroup = Regroup{ acc: 0, bits: 0, pos: 0 };
+    for (i, c) in data.iter().enumerate() {
+        let (val, ok) = decode_char(*c, &mut case);
+        valid &= ok;
+        chk = polymod_step(chk, val);
+        if i < payload {
+            regroup.push(out, val);
+        }
+    }
+    valid &= regroup.finish();
+    valid &= !case.is_mixed();
+    valid &= ct_u32_eq(chk, variant.constant());
+    (valid, bytes)
+}
+#[test]
+fn test_ct_bech32() {
+    //BIP 173 and BIP 350 valid strings
+    let mut out = [0u8;40];
+    assert_eq!( ct_bech32_decode(&mut out, b"a", b"2uel5l", Bech32Variant::Bech32), (true, 0));
+    assert_eq!( ct_bech32_decode(&mut out, b"A", b"2UEL5L", Bech32Variant::Bech32), (true, 0));
+    assert_eq!( ct_bech32_decode(&mut out, b"a", b"lqfn3a", Bech32Variant::Bech32m), (true, 0));
+    assert_eq!( ct_bech32_decode(&mut out, b"a", b"lqfn3a", Bech32Variant::Bech32).0, false);
+    let data = b"qpzry9x8gf2tvdw0s3jn54khce6mua7lmqqqxw";
+    let (ok, len) = ct_bech32_decode(&mut out, b"abcdef", data, Bech32Variant::Bech32);
+    assert_eq!( (ok, len), (true, 20));
+    let mut u5 = [0u8;38];
+    assert_eq!( ct_bech32_to_u5(&mut u5, data), true);
+    for i in 0..32 {
+        assert_eq!( u5[i], i as u8);
+    }
+    assert_eq!( ct_bech32_verify_checksum(b"abcdef", &u5, Bech32Variant::Bech32), true);
+    u5[3] ^= 1;
+    assert_eq!( ct_bech32_verify_checksum(b"abcdef", &u5, Bech32Variant::Bech32), false);
+
+    //segwit v0, the version is the first value and the program follows
+    let addr = b"QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4";
+    let mut u5 = [0u8;39];
+    assert_eq!( ct_bech32_to_u5(&mut u5, addr), true);
+    assert_eq!( ct_bech32_verify_checksum(b"BC", &u5, Bech32Variant::Bech32), true);
+    assert_eq!( u5[0], 0);
+    let mut program = [0u8;20];
+    assert_eq!( ct_bech32_convert_bits(&mut program, &u5[1..33]), true);
+    let want: [u8;20] = [0x75,0x1e,0x76,0xe8,0x19,0x91,0x96,0xd4,0x54,0x94,
+        0x1c,0x45,0xd1,0xb3,0xa3,0x23,0xf1,0x43,0x3b,0xd6];
+    assert_eq!( program, want);
+
+    //mixed case, bad characters and a flipped character
+    assert_eq!( ct_bech32_decode(&mut out, b"a", b"2uEl5l", Bech32Variant::Bech32).0, false);
+    assert_eq!( ct_bech32_decode(&mut out, b"a", b"2ubl5l", Bech32Variant::Bech32).0, false);
+    assert_eq!( ct_bech32_decode(&mut out, b"a", b"2uel5m", Bech32Variant::Bech32).0, false);
+    assert_eq!( ct_bech32_decode(&mut out, b"a", b"2uel5", Bech32Variant::Bech32).0, false);
+    //non-zero padding bits
+    let mut byte = [0u8;1];
+    assert_eq!( ct_bech32_convert_bits(&mut byte, &[31, 28]), true);
+    assert_eq!( byte, [0xFF]);
+    assert_eq!( ct_bech32_convert_bits(&mut byte, &[31, 31]), false);
+}
@@ -0,0 +1,78 @@
+//Copyright 2016 William Cody Laeder
+//
+//Licensed under the Apache License, Version 2.0 (the "License");
+//you may not use this file except in compliance with the License.
+//you may obtain a copy of the License at
+//
+//  http://www.apache.org/licenses/LICENSE-2.0
+//
+//Unless required by applicable law or agreed to in writing, software
+//distrubuted under the License is distrubuted on as "AS IS" BASIS,
+//WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//See the License for the specific language governing permissions and
+//limitations under the License.
+
+
+//!Cycle measurement
+//!
+//!Enabled with the `bench` feature. A small helper so downstream code
+//!can regression test both the speed and the timing variance of these
+//!primitives on its own hardware. Only x86_64 (RDTSC) and aarch64
+//!(CNTVCT_EL0) are supported.
+//!
+//!The counters are not cycle exact. RDTSC ticks at a constant rate on
+//!modern processors and CNTVCT at the (usually much slower) system
+//!counter frequency. Compare measurements against each other, not
+//!against datasheet cycle counts.
+
+/*
+ * The fence/barrier before reading the counter keeps earlier
+ * instructions from being reordered past the read.
+ */
+#[cfg(target_arch = "x86_64")]
+#[inline(always)]
+fn counter() -> u64 {
+    unsafe {
+        ::core::arch::x86_64::_mm_lfence();
+        ::core::arch::x86_64::_rdtsc()
+    }
+}
+#[cfg(target_arch = "aarch64")]
+#[inline(always)]
+fn counter() -> u64 {
+    let val: u64;
+    unsafe {
+        ::core::arch::asm!("isb", "mrs {}, cntvct_el0", out(reg) val, options(nomem, nostack));
+    }
+    val
+}
+
+///Measure a single call of `f` in counter ticks.
+///
+///The result includes the overhead of reading the counter twice,
+///measure an empty closure to find it.
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+pub fn cycles<F>(mut f: F) -> u64
+  where F: FnMut() {
+    let start = counter();
+    f();
+    let end = counter();
+    end.wrapping_sub(start)
+}
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+#[test]
+fn test_cycles() {
+    //only the counter itself is checked, how long the work takes
+    //depends on the load and clock of the machine running the tests
+    let first = counter();
+    let mut x: u64 = 0;
+    let ticks = cycles(|| {
+        for i in 0..1000 {
+            x = super::ct_select_u64(x & 1 == 0, x.wrapping_add(i), x);
+        }
+    });
+    let last = counter();
+    assert!( first != 0);
+    assert!( last >= first);
+    assert!( ticks <= last - first);
+}
@@ -0,0 +1,156 @@
+//Copyright 2016 William Cody Laeder
+//
+//Licensed under the Apache License, Version 2.0 (the "License");
+//you may not use this file except in compliance with the License.
+//you may obtain a copy of the License at
+//
+//  http://www.apache.org/licenses/LICENSE-2.0
+//
+//Unless required by applicable law or agreed to in writing, software
+//distrubuted under the License is distrubuted on as "AS IS" BASIS,
+//WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//See the License for the specific language governing permissions and
+//limitations under the License.
+
+
+//!Fixed width big integers
+//!
+//!`CtU256` and `CtU512` are unsigned integers stored as little endian
+//!`u64` limbs, with every operation built on the limb helpers. None of
+//!them branch on a value.
+
+use core::cmp::Ordering;
+use super::{ct_select_u64, ct_u64_eq};
+use super::limbs::{ct_add_limbs, ct_sub_limbs, ct_mul_limbs};
+
+macro_rules! ct_uint_gen {
+    ($name:ident,$limbs:expr;;$test_name:ident) => {
+        ///Fixed width unsigned integer, least significant limb first.
+        #[derive(Clone,Copy)]
+        pub struct $name {
+            limbs: [u64; $limbs],
+        }
+        impl $name {
+            ///Zero.
+            pub fn new() -> $name {
+                $name{ limbs: [0; $limbs] }
+            }
+            ///Build from little endian limbs.
+            pub fn from_limbs(limbs: [u64; $limbs]) -> $name {
+                $name{ limbs }
+            }
+            ///Borrow the little endian limbs.
+            pub fn limbs(&self) -> &[u64; $limbs] {
+                &self.limbs
+            }
+            ///Addition, returns the sum modulo `2^BITS` and if it
+            ///overflowed.
+            pub fn overflowing_add(&self, other: &$name) -> ($name, bool) {
+                let mut res = *self;
+                let carry = ct_add_limbs(&mut res.limbs, &other.limbs);
+                (res, unsafe{::core::mem::transmute::<u8,bool>(carry as u8)})
+            }
+            ///Subtraction, returns the difference modulo `2^BITS` and if
+            ///it underflowed.
+            pub fn overflowing_sub(&self, other: &$name) -> ($name, bool) {
+                let mut res = *self;
+                let borrow = ct_sub_limbs(&mut res.limbs, &other.limbs);
+                (res, unsafe{::core::mem::transmute::<u8,bool>(borrow as u8)})
+            }
+            ///Multiplication, returns the low and high halves of the
+            ///full product.
+            pub fn widening_mul(&self, other: &$name) -> ($name, $name) {
+                let mut wide = [0u64; 2 * $limbs];
+                ct_mul_limbs(&mut wide, &self.limbs, &other.limbs);
+                let mut lo = $name::new();
+                let mut hi = $name::new();
+                lo.limbs.copy_from_slice(&wide[..$limbs]);
+                hi.limbs.copy_from_slice(&wide[$limbs..]);
+                (lo, hi)
+            }
+            ///Equality, every limb is compared.
+            pub fn ct_eq(&self, other: &$name) -> bool {
+                let mut diff: u64 = 0;
+                for i in 0..$limbs {
+                    diff |= self.limbs[i] ^ other.limbs[i];
+                }
+                ct_u64_eq(diff, 0)
+            }
+            ///Comparison, the borrow of a full subtraction says `Less`
+            ///and any differing limb says not `Equal`.
+            pub fn ct_cmp(&self, other: &$name) -> Ordering {
+                let mut scratch = self.limbs;
+                let lt = ct_sub_limbs(&mut scratch, &other.limbs) as u8;
+                let ne = unsafe{::core::mem::transmute::<bool,u8>(!self.ct_eq(other))};
+                let gt = ne & (lt ^ 1);
+                unsafe{::core::mem::transmute::<i8,Ordering>(gt.wrapping_sub(lt) as i8)}
+            }
+            ///If `flag` is true return `x`, else `y`.
+            pub fn ct_select(flag: bool, x: &$name, y: &$name) -> $name {
+                let mut res = $name::new();
+                for i in 0..$limbs {
+                    res.limbs[i] = ct_select_u64(flag, x.limbs[i], y.limbs[i]);
+                }
+                res
+            }
+            ///Overwrite with `other` if `flag` is true.
+            ///
+            ///Every limb is rewritten reguardless of the flag.
+            pub fn ct_assign_if(&mut self, flag: bool, other: &$name) {
+                for i in 0..$limbs {
+                    self.limbs[i] = ct_select_u64(flag, other.limbs[i], self.limbs[i]);
+                }
+            }
+        }
+        impl Default for $name {
+            fn default() -> $name {
+                $name::new()
+            }
+        }
+        #[test]
+        fn $test_name() {
+            let zero = $name::new();
+            let one = {
+                let mut l = [0u64; $limbs];
+                l[0] = 1;
+                $name::from_limbs(l)
+            };
+            let max = $name::from_limbs([u64::MAX; $limbs]);
+            let (sum, over) = max.overflowing_add(&one);
+            assert_eq!( sum.limbs(), zero.limbs());
+            assert_eq!( over, true);
+            let (sum, over) = one.overflowing_add(&one);
+            assert_eq!( sum.limbs()[0], 2);
+            assert_eq!( over, false);
+            let (diff, under) = zero.overflowing_sub(&one);
+            assert_eq!( diff.limbs(), max.limbs());
+            assert_eq!( under, true);
+            let (diff, under) = max.overflowing_sub(&max);
+            assert_eq!( diff.limbs(), zero.limbs());
+            assert_eq!( under, false);
+            //(2^BITS - 1)^2 = 2^BITS * (2^BITS - 2) + 1
+            let (lo, hi) = max.widening_mul(&max);
+            assert_eq!( lo.limbs(), one.limbs());
+            let (hi_want, _) = max.overflowing_sub(&one);
+            assert_eq!( hi.limbs(), hi_want.limbs());
+            assert_eq!( zero.ct_cmp(&one), Ordering::Less);
+            assert_eq!( max.ct_cmp(&one), Ordering::Greater);
+            assert_eq!( one.ct_cmp(&one), Ordering::Equal);
+            assert_eq!( one.ct_eq(&one), true);
+            assert_eq!( one.ct_eq(&max), false);
+            let mut high_bit = $name::new();
+            high_bit.limbs[$limbs - 1] = 1;
+            assert_eq!( high_bit.ct_cmp(&max), Ordering::Less);
+            assert_eq!( high_bit.ct_cmp(&one), Ordering::Greater);
+            assert_eq!( $name::ct_select(true, &one, &max).limbs(), one.limbs());
+            assert_eq!( $name::ct_select(false, &one, &max).limbs(), max.limbs());
+            let mut x = one;
+            x.ct_assign_if(false, &max);
+            assert_eq!( x.limbs(), one.limbs());
+            x.ct_assign_if(true, &max);
+            assert_eq!( x.limbs(), max.limbs());
+        }
+    }
+}
+ct_uint_gen!(CtU256,4;;test_ct_u256);
+ct_uint_gen!(CtU512,8;;test_ct_u512);
@@ -0,0 +1,143 @@
+//Copyright 2016 William Cody Laeder
+//
+//Licensed under the Apache License, Version 2.0 (the "License");
+//you may not use this file except in compliance with the License.
+//you may obtain a copy of the License at
+//
+//  http://www.apache.org/licenses/LICENSE-2.0
+//
+//Unless required by applicable law or agreed to in writing, software
+//distrubuted under the License is distrubuted on as "AS IS" BASIS,
+//WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//See the License for the specific language governing permissions and
+//limitations under the License.
+
+
+//!Bitslicing
+//!
+//!Bit matrix transposes and packing helpers for moving byte oriented
+//!state into bitsliced form, where bit `j` of many inputs lives in one
+//!word and a cipher runs as pure boolean logic. Element `(i, j)` of a
+//!matrix is bit `j` of row `i`, with bit 0 the least significant.
+//!Everything here is shifts, masks and XORs in a fixed order.
+
+/*
+ * Exchange the bits selected by `mask` with the ones `shift` above
+ * them.
+ */
+#[inline(always)]
+fn delta_swap(x: u64, mask: u64, shift: u32) -> u64 {
+    let t = ((x >> shift) ^ x) & mask;
+    x ^ t ^ (t << shift)
+}
+
+///Transpose an 8x8 bit matrix, row `i` is byte `i` of `x` counting
+///from the least significant.
+pub fn ct_transpose_8x8(x: u64) -> u64 {
+    let x = delta_swap(x, 0x00AA00AA00AA00AA, 7);
+    let x = delta_swap(x, 0x0000CCCC0000CCCC, 14);
+    delta_swap(x, 0x00000000F0F0F0F0, 28)
+}
+
+/*
+ * Recursive block transpose. For each block size j from half the width
+ * down to one, the top right j by j block of every 2j by 2j block is
+ * exchanged with the bottom left one, m selects the low j bits of
+ * every 2j.
+ */
+macro_rules! ct_transpose_gen {
+    ($name:ident,$code:ident,$bits:expr,$mask:expr;;$test_name:ident) => {
+        ///Transpose a square bit matrix in place, row `i` is `x[i]`.
+        pub fn $name(x: &mut [$code; $bits]) {
+            let mut j: usize = $bits / 2;
+            let mut m: $code = $mask;
+            while j != 0 {
+                let mut k: usize = 0;
+                while k < $bits {
+                    let t = ((x[k] >> j) ^ x[k + j]) & m;
+                    x[k] ^= t << j;
+                    x[k + j] ^= t;
+                    k = (k + j + 1) & !j;
+                }
+                j >>= 1;
+                m ^= m << j;
+            }
+        }
+        #[test]
+        fn $test_name() {
+            let mut state: u64 = 7;
+            let mut x = [0 as $code; $bits];
+            for i in 0..$bits {
+                x[i] = ((lcg(&mut state) << 32) ^ lcg(&mut state)) as $code;
+            }
+            let orig = x;
+            $name(&mut x);
+            for i in 0..$bits {
+                for j in 0..$bits {
+                    assert_eq!( (x[i] >> j) & 1, (orig[j] >> i) & 1);
+                }
+            }
+            $name(&mut x);
+            assert_eq!( x, orig);
+        }
+    }
+}
+ct_transpose_gen!(ct_transpose_32x32,u32,32,0x0000FFFF;;test_ct_transpose_32x32);
+ct_transpose_gen!(ct_transpose_64x64,u64,64,0x00000000FFFFFFFF;;test_ct_transpose_64x64);
+
+///Pack 64 bytes into 8 bit planes, bit `i` of plane `j` is bit `j` of
+///byte `i`.
+pub fn ct_bitslice_pack(bytes: &[u8; 64]) -> [u64; 8] {
+    let mut planes = [0u64; 8];
+    for (i, b) in bytes.iter().enumerate() {
+        for (j, plane) in planes.iter_mut().enumerate() {
+            *plane |= (((*b >> j) & 1) as u64) << i;
+        }
+    }
+    planes
+}
+///Unpack 8 bit planes back into 64 bytes, the inverse of
+///`ct_bitslice_pack`.
+pub fn ct_bitslice_unpack(planes: &[u64; 8]) -> [u8; 64] {
+    let mut bytes = [0u8; 64];
+    for (i, b) in bytes.iter_mut().enumerate() {
+        for (j, plane) in planes.iter().enumerate() {
+            *b |= (((*plane >> i) & 1) as u8) << j;
+        }
+    }
+    bytes
+}
+
+#[cfg(test)]
+fn lcg(state: &mut u64) -> u64 {
+    *state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+    *state >> 33
+}
+#[test]
+fn test_ct_transpose_8x8() {
+    let mut state: u64 = 3;
+    for _ in 0..64 {
+        let x = (lcg(&mut state) << 32) ^ lcg(&mut state) ^ (lcg(&mut state) << 40);
+        let y = ct_transpose_8x8(x);
+        for i in 0..8 {
+            for j in 0..8 {
+                assert_eq!( (y >> (8 * i + j)) & 1, (x >> (8 * j + i)) & 1);
+            }
+        }
+        assert_eq!( ct_transpose_8x8(y), x);
+    }
+}
+#[test]
+fn test_ct_bitslice_pack() {
+    let mut bytes = [0u8;64];
+    for i in 0..64 {
+        bytes[i] = (i as u8).wrapping_mul(37) ^ 0x5A;
+    }
+    let planes = ct_bitslice_pack(&bytes);
+    for i in 0..64 {
+        for j in 0..8 {
+            assert_eq!( ((planes[j] >> i) & 1) as u8, (bytes[i] >> j) & 1);
+        }
+    }
+    assert_eq!( &ct_bitslice_unpack(&planes)[..], &bytes[..]);
+}
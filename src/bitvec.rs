@@ -0,0 +1,235 @@
+//Copyright 2016 William Cody Laeder
+//
+//Licensed under the Apache License, Version 2.0 (the "License");
+//you may not use this file except in compliance with the License.
+//you may obtain a copy of the License at
+//
+//  http://www.apache.org/licenses/LICENSE-2.0
+//
+//Unless required by applicable law or agreed to in writing, software
+//distrubuted under the License is distrubuted on as "AS IS" BASIS,
+//WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//See the License for the specific language governing permissions and
+//limitations under the License.
+
+
+//!Constant time bit vectors
+//!
+//!A fixed capacity bit vector whose bits are addressed by secret
+//!indexes. Every access touches every word.
+
+use super::{ct_select_u64, ct_select_usize, ct_u64_lt, ct_usize_eq};
+use super::limbs::ct_shl_slice;
+
+/*
+ * Branch free population count of a word, the same SWAR reduction
+ * used for bytes extended to 64 bits.
+ */
+fn popcount_u64(x: u64) -> u64 {
+    let x = x - ((x >> 1) & 0x5555555555555555);
+    let x = (x & 0x3333333333333333) + ((x >> 2) & 0x3333333333333333);
+    let x = (x + (x >> 4)) & 0x0F0F0F0F0F0F0F0F;
+    x.wrapping_mul(0x0101010101010101) >> 56
+}
+#[test]
+fn test_popcount_u64() {
+    let vals: [u64;6] = [0, 1, u64::MAX, 0x8000000000000001, 0xF0F0, 0x0123456789ABCDEF];
+    for i in 0..vals.len() {
+        assert_eq!( popcount_u64(vals[i]) as u32, vals[i].count_ones());
+    }
+}
+
+///A bit vector of `W` 64bit words.
+///
+///The capacity, `W * 64` bits, is public. Bit indexes are secret, an
+///out of range index reads as `false` and writes nothing.
+#[derive(Clone)]
+pub struct CtBitVec<const W: usize> {
+    words: [u64; W],
+}
+impl<const W: usize> CtBitVec<W> {
+    ///Build a bit vector with every bit cleared.
+    pub fn new() -> CtBitVec<W> {
+        CtBitVec{ words: [0; W] }
+    }
+    ///Number of bits, this is public.
+    pub fn capacity(&self) -> usize {
+        W * 64
+    }
+    ///Read the bit at a secret index.
+    pub fn get(&self, index: usize) -> bool {
+        let word_index = index / 64;
+        let shift = (index % 64) as u32;
+        let mut word: u64 = 0;
+        for i in 0..W {
+            word |= ct_select_u64(ct_usize_eq(i, word_index), self.words[i], 0);
+        }
+        let bit = ((word >> shift) & 1) as u8;
+        unsafe{::core::mem::transmute::<u8,bool>(bit)}
+    }
+    ///Set the bit at a secret index if `flag` is true.
+    ///
+    ///Every word is rewritten reguardless of the flag.
+    pub fn set_if(&mut self, flag: bool, index: usize) {
+        let word_index = index / 64;
+        let bit = 1u64 << (index % 64);
+        for i in 0..W {
+            let hit = flag & ct_usize_eq(i, word_index);
+            self.words[i] |= ct_select_u64(hit, bit, 0);
+        }
+    }
+    ///Clear the bit at a secret index if `flag` is true.
+    ///
+    ///Every word is rewritten reguardless of the flag.
+    pub fn clear_if(&mut self, flag: bool, index: usize) {
+        let word_index = index / 64;
+        let bit = 1u64 << (index % 64);
+        for i in 0..W {
+            let hit = flag & ct_usize_eq(i, word_index);
+            self.words[i] &= !ct_select_u64(hit, bit, 0);
+        }
+    }
+    ///Number of set bits.
+    pub fn popcount(&self) -> usize {
+        let mut count: u64 = 0;
+        for i in 0..W {
+            count += popcount_u64(self.words[i]);
+        }
+        count as usize
+    }
+    ///Bitwise AND with another vector, in place.
+    pub fn and_assign(&mut self, other: &CtBitVec<W>) {
+        for i in 0..W {
+            self.words[i] &= other.words[i];
+        }
+    }
+    ///Bitwise OR with another vector, in place.
+    pub fn or_assign(&mut self, other: &CtBitVec<W>) {
+        for i in 0..W {
+            self.words[i] |= other.words[i];
+        }
+    }
+    ///Bitwise XOR with another vector, in place.
+    pub fn xor_assign(&mut self, other: &CtBitVec<W>) {
+        for i in 0..W {
+            self.words[i] ^= other.words[i];
+        }
+    }
+    ///Invert every bit, in place.
+    pub fn not_assign(&mut self) {
+        for i in 0..W {
+            self.words[i] = !self.words[i];
+        }
+    }
+}
+impl<const W: usize> Default for CtBitVec<W> {
+    fn default() -> CtBitVec<W> {
+        CtBitVec::new()
+    }
+}
+#[test]
+fn test_ct_bit_vec() {
+    let mut x: CtBitVec<2> = CtBitVec::new();
+    assert_eq!( x.capacity(), 128);
+    assert_eq!( x.get(5), false);
+    x.set_if(true, 5);
+    x.set_if(false, 6);
+    x.set_if(true, 127);
+    x.set_if(true, 128);
+    assert_eq!( x.get(5), true);
+    assert_eq!( x.get(6), false);
+    assert_eq!( x.get(127), true);
+    assert_eq!( x.get(128), false);
+    assert_eq!( x.popcount(), 2);
+    x.clear_if(false, 5);
+    assert_eq!( x.get(5), true);
+    x.clear_if(true, 5);
+    assert_eq!( x.get(5), false);
+    let mut y: CtBitVec<2> = CtBitVec::new();
+    y.set_if(true, 64);
+    y.set_if(true, 127);
+    let mut z = x.clone();
+    z.or_assign(&y);
+    assert_eq!( z.popcount(), 2);
+    z.and_assign(&x);
+    assert_eq!( z.popcount(), 1);
+    z.xor_assign(&y);
+    assert_eq!( z.get(64), true);
+    assert_eq!( z.get(127), false);
+    z.not_assign();
+    assert_eq!( z.popcount(), 127);
+}
+
+///Sliding anti-replay window.
+///
+///Tracks the highest sequence number accepted so far and a `W * 64`
+///bit window of the ones below it, in the style of DTLS and ESP. Bit
+///`i` of the window records if `top - i` was seen.
+#[derive(Clone)]
+pub struct ReplayWindow<const W: usize> {
+    top: u64,
+    seen: CtBitVec<W>,
+}
+impl<const W: usize> ReplayWindow<W> {
+    ///Build a window in which nothing has been seen.
+    pub fn new() -> ReplayWindow<W> {
+        ReplayWindow{ top: 0, seen: CtBitVec::new() }
+    }
+    ///Check a sequence number and record it if it is accepted.
+    ///
+    ///A sequence number is accepted if it is above every number seen
+    ///so far, or if it falls inside of the window and was not seen
+    ///yet. Anything else is a replay or too old.
+    ///
+    ///Both outcomes do the same work. The window is always shifted
+    ///into a copy, then committed or discarded with masks, and the
+    ///bit is always written with `set_if`.
+    pub fn check_and_update(&mut self, seq: u64) -> bool {
+        let cap = (W * 64) as u64;
+        let ahead = ct_u64_lt(self.top, seq);
+        let behind = self.top.wrapping_sub(seq);
+        let forward = seq.wrapping_sub(self.top);
+        let in_window = !ahead & ct_u64_lt(behind, cap);
+        let index = ct_select_usize(in_window, behind as usize, W * 64);
+        let accept = ahead | (in_window & !self.seen.get(index));
+
+        let mut shifted = self.seen.words;
+        let shift = ct_select_u64(ct_u64_lt(forward, cap), forward, cap);
+        ct_shl_slice(&mut shifted, shift as usize);
+        for i in 0..W {
+            self.seen.words[i] = ct_select_u64(ahead, shifted[i], self.seen.words[i]);
+        }
+        self.seen.set_if(accept, ct_select_usize(ahead, 0, index));
+        self.top = ct_select_u64(ahead, seq, self.top);
+        accept
+    }
+}
+impl<const W: usize> Default for ReplayWindow<W> {
+    fn default() -> ReplayWindow<W> {
+        ReplayWindow::new()
+    }
+}
+#[test]
+fn test_replay_window() {
+    let mut w: ReplayWindow<1> = ReplayWindow::new();
+    assert_eq!( w.check_and_update(1), true);
+    assert_eq!( w.check_and_update(1), false);
+    assert_eq!( w.check_and_update(3), true);
+    assert_eq!( w.check_and_update(2), true);
+    assert_eq!( w.check_and_update(2), false);
+    assert_eq!( w.check_and_update(3), false);
+    assert_eq!( w.check_and_update(66), true);
+    assert_eq!( w.check_and_update(3), false);
+    assert_eq!( w.check_and_update(2), false);
+    assert_eq!( w.check_and_update(4), true);
+    assert_eq!( w.check_and_update(4), false);
+    assert_eq!( w.check_and_update(1000), true);
+    assert_eq!( w.check_and_update(66), false);
+    assert_eq!( w.check_and_update(999), true);
+    assert_eq!( w.check_and_update(937), true);
+    assert_eq!( w.check_and_update(936), false);
+    assert_eq!( w.check_and_update(u64::MAX), true);
+    assert_eq!( w.check_and_update(u64::MAX), false);
+    assert_eq!( w.check_and_update(u64::MAX - 63), true);
+    assert_eq!( w.check_and_update(1000), false);
+}
@@ -0,0 +1,57 @@
+//Copyright 2016 William Cody Laeder
+//
+//Licensed under the Apache License, Version 2.0 (the "License");
+//you may not use this file except in compliance with the License.
+//you may obtain a copy of the License at
+//
+//  http://www.apache.org/licenses/LICENSE-2.0
+//
+//Unless required by applicable law or agreed to in writing, software
+//distrubuted under the License is distrubuted on as "AS IS" BASIS,
+//WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//See the License for the specific language governing permissions and
+//limitations under the License.
+
+
+//!Table free CRCs
+//!
+//!The usual CRC32 indexes a 256 entry table with every input byte,
+//!which leaks the data through the cache. These process one bit at a
+//!time and apply the polynomial under a mask, eight fixed steps per
+//!byte.
+
+macro_rules! ct_crc_gen {
+    ($name:ident,$update:ident,$poly:expr,$doc:expr;;$test_name:ident,$check:expr) => {
+        #[doc=$doc]
+        ///
+        ///Continue a running CRC with more data. Start with `0`, the
+        ///initial and final inversions are handled here.
+        pub fn $update(crc: u32, data: &[u8]) -> u32 {
+            let mut crc = !crc;
+            for b in data.iter() {
+                crc ^= *b as u32;
+                for _ in 0..8 {
+                    crc = (crc >> 1) ^ ($poly & (crc & 1).wrapping_neg());
+                }
+            }
+            !crc
+        }
+        #[doc=$doc]
+        pub fn $name(data: &[u8]) -> u32 {
+            $update(0, data)
+        }
+        #[test]
+        fn $test_name() {
+            assert_eq!( $name(b"123456789"), $check);
+            assert_eq!( $name(b""), 0);
+            let crc = $update(0, b"1234");
+            assert_eq!( $update(crc, b"56789"), $check);
+        }
+    }
+}
+ct_crc_gen!(ct_crc32,ct_crc32_update,0xEDB88320,
+    "CRC-32 as used by zlib and Ethernet, reflected polynomial `0xEDB88320`.";;
+    test_ct_crc32,0xCBF43926);
+ct_crc_gen!(ct_crc32c,ct_crc32c_update,0x82F63B78,
+    "CRC-32C (Castagnoli) as used by iSCSI and ext4, reflected polynomial `0x82F63B78`.";;
+    test_ct_crc32c,0xE3069283);
@@ -0,0 +1,142 @@
+//Copyright 2016 William Cody Laeder
+//
+//Licensed under the Apache License, Version 2.0 (the "License");
+//you may not use this file except in compliance with the License.
+//you may obtain a copy of the License at
+//
+//  http://www.apache.org/licenses/LICENSE-2.0
+//
+//Unless required by applicable law or agreed to in writing, software
+//distrubuted under the License is distrubuted on as "AS IS" BASIS,
+//WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//See the License for the specific language governing permissions and
+//limitations under the License.
+
+
+//!DER headers
+//!
+//!Parsing of ASN.1 DER tag and length headers inside buffers that hold
+//!secrets, such as private keys, where the lengths of the fields give
+//!away the size of the numbers in them. The header is read at a
+//!secret offset with a full scan of the buffer, every rule is checked
+//!and folded into one flag, and the outputs are masked to zero on
+//!failure. Only single byte tags and lengths of up to 4 bytes are
+//!supported, which covers every key format in use.
+
+use super::{ct_u8_eq, ct_usize_eq, ct_usize_lt, ct_select_u8, ct_select_usize};
+
+/* tag, first length byte and up to four more length bytes */
+const MAX_HEADER_LEN: usize = 6;
+
+///A parsed tag and length header.
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub struct DerHeader {
+    ///The tag byte, class, constructed bit and tag number.
+    pub tag: u8,
+    ///Length of the contents.
+    pub len: usize,
+    ///Length of the header, the contents start this far after it.
+    pub header_len: usize,
+}
+
+/*
+ * Read MAX_HEADER_LEN bytes starting at `offset`, each with a full
+ * scan of `buf`. Positions past the end read as zero.
+ */
+fn load_header(buf: &[u8], offset: usize) -> [u8; MAX_HEADER_LEN] {
+    let mut window = [0u8; MAX_HEADER_LEN];
+    for (i, &b) in buf.iter().enumerate() {
+        for (j, w) in window.iter_mut().enumerate() {
+            *w |= ct_select_u8(ct_usize_eq(i, offset.wrapping_add(j)), b, 0);
+        }
+    }
+    window
+}
+
+///Parse the DER header at `offset` in `buf`.
+///
+///Returns if the header is valid and the header. It is valid when the
+///tag is in the low tag number form, the length is in its shortest
+///DER form with at most 4 length bytes, and the header and contents
+///fit in `buf`. Every case does the same work, `6 * buf.len()` masked
+///byte reads and a fixed sequence of checks. On failure the header is
+///all zeros.
+pub fn ct_der_header(buf: &[u8], offset: usize) -> (bool, DerHeader) {
+    let w = load_header(buf, offset);
+    let avail = ct_select_usize(ct_usize_lt(buf.len(), offset), 0, buf.len().wrapping_sub(offset));
+    let tag = w[0];
+    let first = w[1];
+    let long = !ct_usize_lt(first as usize, 0x80);
+    let n = (first & 0x7F) as usize;
+    let mut valid = !ct_u8_eq(tag & 0x1F, 0x1F);
+    //long form needs 1 to 4 length bytes and no leading zero byte
+    valid &= !(long & (ct_usize_eq(n, 0) | ct_usize_lt(4, n)));
+    valid &= !(long & ct_u8_eq(w[2], 0));
+    let mut long_len: usize = 0;
+    for j in 0..4 {
+        let used = ct_usize_lt(j, n);
+        long_len = ct_select_usize(used, (long_len << 8) | (w[2 + j] as usize), long_len);
+    }
+    //a single length byte below 0x80 should have been the short form
+    valid &= !(long & ct_usize_eq(n, 1) & ct_usize_lt(long_len, 0x80));
+    let len = ct_select_usize(long, long_len, first as usize);
+    let header_len = ct_select_usize(long, 2 + n, 2);
+    //the header bytes must be in range before the contents are checked
+    valid &= !ct_usize_lt(avail, header_len);
+    let rest = avail.wrapping_sub(header_len);
+    valid &= !ct_usize_lt(rest, len);
+    (valid, DerHeader {
+        tag: ct_select_u8(valid, tag, 0),
+        len: ct_select_usize(valid, len, 0),
+        header_len: ct_select_usize(valid, header_len, 0),
+    })
+}
+///Parse the DER header at `offset` and require the tag to be `tag`.
+///
+///The tag check is folded into the same flag as the rest of
+///`ct_der_header`.
+pub fn ct_der_expect(buf: &[u8], offset: usize, tag: u8) -> (bool, DerHeader) {
+    let (valid, hdr) = ct_der_header(buf, offset);
+    let valid = valid & ct_u8_eq(hdr.tag, tag);
+    (valid, DerHeader {
+        tag: ct_select_u8(valid, hdr.tag, 0),
+        len: ct_select_usize(valid, hdr.len, 0),
+        header_len: ct_select_usize(valid, hdr.header_len, 0),
+    })
+}
+#[test]
+fn test_ct_der_header() {
+    let hdr = |tag, len, header_len| DerHeader{ tag, len, header_len };
+    let none = DerHeader::default();
+    //SEQUENCE { INTEGER 5 }
+    let seq = [0x30u8, 0x03, 0x02, 0x01, 0x05];
+    assert_eq!( ct_der_header(&seq, 0), (true, hdr(0x30, 3, 2)));
+    assert_eq!( ct_der_header(&seq, 2), (true, hdr(0x02, 1, 2)));
+    assert_eq!( ct_der_expect(&seq, 2, 0x02), (true, hdr(0x02, 1, 2)));
+    assert_eq!( ct_der_expect(&seq, 2, 0x04), (false, none));
+    //long forms
+    let mut long = [0u8;300];
+    long[..4].copy_from_slice(&[0x04, 0x82, 0x01, 0x00]);
+    assert_eq!( ct_der_header(&long, 0), (true, hdr(0x04, 256, 4)));
+    long[..3].copy_from_slice(&[0x04, 0x81, 0x80]);
+    assert_eq!( ct_der_header(&long, 0), (true, hdr(0x04, 128, 3)));
+    long[..6].copy_from_slice(&[0x04, 0x84, 0x00, 0x00, 0x01, 0x00]);
+    assert_eq!( ct_der_header(&long, 0), (false, none));
+    //not minimal, indefinite, too many length bytes, high tag number
+    let bad: [&[u8];6] = [
+        &[0x04, 0x81, 0x05, 0, 0, 0, 0, 0],
+        &[0x04, 0x80, 0x00, 0x00],
+        &[0x04, 0x85, 0x01, 0x00, 0x00, 0x00, 0x00],
+        &[0x1F, 0x01, 0x00],
+        &[0x04, 0x05, 0x00],
+        &[0x04],
+    ];
+    for b in bad.iter() {
+        assert_eq!( ct_der_header(b, 0), (false, none));
+    }
+    //offsets at and past the end
+    assert_eq!( ct_der_header(&seq, 4), (false, none));
+    assert_eq!( ct_der_header(&seq, 5), (false, none));
+    assert_eq!( ct_der_header(&seq, usize::MAX), (false, none));
+    assert_eq!( ct_der_header(&[0x05, 0x00], 0), (true, hdr(0x05, 0, 2)));
+}
@@ -0,0 +1,74 @@
+//Copyright 2016 William Cody Laeder
+//
+//Licensed under the Apache License, Version 2.0 (the "License");
+//you may not use this file except in compliance with the License.
+//you may obtain a copy of the License at
+//
+//  http://www.apache.org/licenses/LICENSE-2.0
+//
+//Unless required by applicable law or agreed to in writing, software
+//distrubuted under the License is distrubuted on as "AS IS" BASIS,
+//WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//See the License for the specific language governing permissions and
+//limitations under the License.
+
+
+//!Endian loads and stores
+//!
+//!Conversions between byte arrays and integers for hash and cipher
+//!cores. Each is a fixed sequence of byte shifts and ORs on an array
+//!of known size, there is no alignment requirement and nothing that
+//!can panic.
+
+macro_rules! ct_endian_gen {
+    ($load:ident,$store:ident,$code:ident,$len:expr,$doc:expr;;$test_name:ident,$order:expr;;$($i:expr),*) => {
+        ///Load a
+        #[doc=$doc]
+        ///integer.
+        #[inline]
+        pub fn $load(x: &[u8; $len]) -> $code {
+            let mut val: $code = 0;
+            $(
+                val |= (x[$i] as $code) << (8 * $order($i, $len));
+            )*
+            val
+        }
+        ///Store a
+        #[doc=$doc]
+        ///integer.
+        #[inline]
+        pub fn $store(out: &mut [u8; $len], val: $code) {
+            $(
+                out[$i] = (val >> (8 * $order($i, $len))) as u8;
+            )*
+        }
+        #[test]
+        fn $test_name() {
+            let vals: [$code;4] = [0, 1, max!($code), 0x0123456789ABCDEFu64 as $code];
+            for &v in vals.iter() {
+                let mut out = [0u8; $len];
+                $store(&mut out, v);
+                let want = if $order(0, 2) == 0 { v.to_le_bytes() } else { v.to_be_bytes() };
+                assert_eq!( out, want);
+                assert_eq!( $load(&out), v);
+            }
+        }
+    }
+}
+/*
+ * Significance of byte i of an n byte integer.
+ */
+const fn le(i: usize, _n: usize) -> usize {
+    i
+}
+const fn be(i: usize, n: usize) -> usize {
+    n - 1 - i
+}
+ct_endian_gen!(ct_load_u32_le,ct_store_u32_le,u32,4,"little endian 32bit";;
+    test_ct_u32_le,le;;0,1,2,3);
+ct_endian_gen!(ct_load_u32_be,ct_store_u32_be,u32,4,"big endian 32bit";;
+    test_ct_u32_be,be;;0,1,2,3);
+ct_endian_gen!(ct_load_u64_le,ct_store_u64_le,u64,8,"little endian 64bit";;
+    test_ct_u64_le,le;;0,1,2,3,4,5,6,7);
+ct_endian_gen!(ct_load_u64_be,ct_store_u64_be,u64,8,"big endian 64bit";;
+    test_ct_u64_be,be;;0,1,2,3,4,5,6,7);
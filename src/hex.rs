@@ -0,0 +1,176 @@
+//Copyright 2016 William Cody Laeder
+//
+//Licensed under the Apache License, Version 2.0 (the "License");
+//you may not use this file except in compliance with the License.
+//you may obtain a copy of the License at
+//
+//  http://www.apache.org/licenses/LICENSE-2.0
+//
+//Unless required by applicable law or agreed to in writing, software
+//distrubuted under the License is distrubuted on as "AS IS" BASIS,
+//WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//See the License for the specific language governing permissions and
+//limitations under the License.
+
+
+//!Hex encoding
+//!
+//!Hex codec for secret data. Characters are classified with range
+//!masks instead of a lookup table or a `match`, and decoding errors
+//!are gathered into one flag that is returned after the whole input
+//!was read.
+
+use super::{ct_u8_lt, ct_select_u8, ct_u8_slice_eq};
+
+/*
+ * Value of one hex digit and if it was one. Letters are case folded
+ * with `| 0x20`, which only maps 'A'..'F' onto 'a'..'f' inside the
+ * letter range that is checked.
+ */
+fn decode_nibble(c: u8) -> (u8, bool) {
+    let lower = c | 0x20;
+    let is_digit = !ct_u8_lt(c, b'0') & ct_u8_lt(c, b'9' + 1);
+    let is_alpha = !ct_u8_lt(lower, b'a') & ct_u8_lt(lower, b'f' + 1);
+    let val = ct_select_u8(is_digit, c.wrapping_sub(b'0'), 0)
+        | ct_select_u8(is_alpha, lower.wrapping_sub(b'a' - 10), 0);
+    (val, is_digit | is_alpha)
+}
+/*
+ * Lowercase hex digit of a nibble, '0' + n with the gap up to 'a'
+ * added under a mask.
+ */
+fn encode_nibble(n: u8) -> u8 {
+    n + b'0' + ct_select_u8(ct_u8_lt(9, n), b'a' - b'0' - 10, 0)
+}
+
+///Encode bytes as lowercase hex.
+///
+///#Panic:
+///
+///This function will panic if `out` is not twice as long as `data`.
+pub fn ct_hex_encode(out: &mut [u8], data: &[u8]) {
+    if out.len() != 2 * data.len() {
+        panic!("Consistent Time: Attempted hex encode into a mis-sized output");
+    }
+    for (pair, b) in out.chunks_mut(2).zip(data.iter()) {
+        pair[0] = encode_nibble(b >> 4);
+        pair[1] = encode_nibble(b & 0x0F);
+    }
+}
+///Decode hex, upper, lower and mixed case are all accepted.
+///
+///Returns if every character was a hex digit. Every character is
+///decoded and written either way, if this returns false the contents
+///of `out` are meaningless.
+///
+///#Panic:
+///
+///This function will panic if `hex` is not twice as long as `out`.
+pub fn ct_hex_decode(out: &mut [u8], hex: &[u8]) -> bool {
+    if hex.len() != 2 * out.len() {
+        panic!("Consistent Time: Attempted hex decode into a mis-sized output");
+    }
+    let mut valid = true;
+    for (b, pair) in out.iter_mut().zip(hex.chunks(2)) {
+        let (hi, hi_ok) = decode_nibble(pair[0]);
+        let (lo, lo_ok) = decode_nibble(pair[1]);
+        *b = (hi << 4) | lo;
+        valid &= hi_ok & lo_ok;
+    }
+    valid
+}
+#[test]
+fn test_ct_hex() {
+    let data: [u8;8] = [0x00, 0x01, 0x7F, 0x80, 0xAB, 0xCD, 0xEF, 0xFF];
+    let mut hex = [0u8;16];
+    ct_hex_encode(&mut hex, &data);
+    assert_eq!( &hex, b"00017f80abcdefff");
+    let mut out = [0u8;8];
+    assert_eq!( ct_hex_decode(&mut out, b"00017f80abcdefff"), true);
+    assert_eq!( out, data);
+    assert_eq!( ct_hex_decode(&mut out, b"00017F80ABCDEFFF"), true);
+    assert_eq!( out, data);
+    assert_eq!( ct_hex_decode(&mut out, b"00017f80AbCdEfFf"), true);
+    assert_eq!( out, data);
+    let bad: [&[u8;2];8] = [b"0g", b"G0", b"/0", b":0", b"@0", b"`0", b" 0", b"0\xC1"];
+    let mut one = [0u8;1];
+    for b in bad.iter() {
+        assert_eq!( ct_hex_decode(&mut one, &b[..]), false);
+    }
+    for c in 0..256 {
+        let c = c as u8;
+        let want = (c as char).is_ascii_hexdigit();
+        assert_eq!( decode_nibble(c).1, want);
+        if want {
+            assert_eq!( decode_nibble(c).0 as u32, (c as char).to_digit(16).unwrap());
+        }
+    }
+    assert_eq!( ct_hex_decode(&mut [], b""), true);
+}
+#[test]
+#[should_panic]
+fn test_ct_hex_decode_panic() {
+    let mut out = [0u8;2];
+    ct_hex_decode(&mut out, b"000");
+}
+
+///Verify a certificate or key fingerprint.
+///
+///`expected` is the raw digest, 20, 32, 48 or 64 bytes. `presented`
+///may be the raw digest or its hex encoding in either case, which one
+///is decided by its (public) length. Hex is decoded with
+///`ct_hex_decode` and the digest is then compared in full, any other
+///length is a mismatch.
+///
+///#Panic:
+///
+///This function will panic if `expected` is not one of the supported
+///digest sizes.
+pub fn ct_verify_fingerprint(expected: &[u8], presented: &[u8]) -> bool {
+    let len = expected.len();
+    if len != 20 && len != 32 && len != 48 && len != 64 {
+        panic!("Consistent Time: Attempted to verify a fingerprint of an unsupported size");
+    }
+    if presented.len() == len {
+        return ct_u8_slice_eq(expected, presented);
+    }
+    if presented.len() != 2 * len {
+        return false;
+    }
+    let mut raw = [0u8;64];
+    let valid = ct_hex_decode(&mut raw[..len], presented);
+    valid & ct_u8_slice_eq(expected, &raw[..len])
+}
+#[test]
+fn test_ct_verify_fingerprint() {
+    let mut digest = [0u8;32];
+    for i in 0..32 {
+        digest[i] = (i as u8).wrapping_mul(29) ^ 0xA5;
+    }
+    let mut hex = [0u8;64];
+    ct_hex_encode(&mut hex, &digest);
+    assert_eq!( ct_verify_fingerprint(&digest, &digest), true);
+    assert_eq!( ct_verify_fingerprint(&digest, &hex), true);
+    let upper: [u8;64] = {
+        let mut u = hex;
+        for c in u.iter_mut() {
+            *c = c.to_ascii_uppercase();
+        }
+        u
+    };
+    assert_eq!( ct_verify_fingerprint(&digest, &upper), true);
+    hex[63] ^= 1;
+    assert_eq!( ct_verify_fingerprint(&digest, &hex), false);
+    hex[63] = b'g';
+    assert_eq!( ct_verify_fingerprint(&digest, &hex), false);
+    let mut other = digest;
+    other[0] ^= 1;
+    assert_eq!( ct_verify_fingerprint(&digest, &other), false);
+    assert_eq!( ct_verify_fingerprint(&digest, &digest[..31]), false);
+    assert_eq!( ct_verify_fingerprint(&[0u8;64], &[b'0';128]), true);
+}
+#[test]
+#[should_panic]
+fn test_ct_verify_fingerprint_panic() {
+    ct_verify_fingerprint(&[0u8;16], &[0u8;16]);
+}
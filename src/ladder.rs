@@ -0,0 +1,103 @@
+//Copyright 2016 William Cody Laeder
+//
+//Licensed under the Apache License, Version 2.0 (the "License");
+//you may not use this file except in compliance with the License.
+//you may obtain a copy of the License at
+//
+//  http://www.apache.org/licenses/LICENSE-2.0
+//
+//Unless required by applicable law or agreed to in writing, software
+//distrubuted under the License is distrubuted on as "AS IS" BASIS,
+//WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//See the License for the specific language governing permissions and
+//limitations under the License.
+
+
+//!Montgomery ladder
+//!
+//!The loop over the scalar bits and the conditional swaps of a
+//!Montgomery ladder, for any group written as `L` limbs. The caller
+//!only provides the group arithmetic, the secret only ever reaches
+//!`ct_swap_slice`.
+
+use super::ct_swap_slice;
+
+///Group operations driven by `ct_ladder`.
+///
+///Neither operation may branch on the value of a point. A
+///differential addition can keep the base point in `self`, the two
+///ladder points always differ by it.
+pub trait LadderOps<const L: usize> {
+    ///Return `2 * p`.
+    fn double(&self, p: &[u64; L]) -> [u64; L];
+    ///Return `p + q`.
+    fn add(&self, p: &[u64; L], q: &[u64; L]) -> [u64; L];
+}
+
+///Run a Montgomery ladder over the low `bits` bits of `scalar`.
+///
+///`r0` and `r1` start as the identity and the base point and end as
+///`scalar * P` and `(scalar + 1) * P`. For every bit from the top the
+///points are swapped if the bit differs from the last one, then
+///`r1 = r0 + r1` and `r0 = 2 * r0`. The swap after the last bit puts
+///them back in order. Both operations run for every bit, `bits` and
+///the length of `scalar` are public.
+///
+///The scalar is little endian limbs.
+///
+///#Panic:
+///
+///This function will panic if `scalar` has fewer than `bits` bits.
+pub fn ct_ladder<O, const L: usize>(ops: &O, r0: &mut [u64; L], r1: &mut [u64; L],
+    scalar: &[u64], bits: usize)
+  where O: LadderOps<L> {
+    if bits > 64 * scalar.len() {
+        panic!("Consistent Time: Attempted ladder over more bits than the scalar holds");
+    }
+    let mut prev: u64 = 0;
+    for i in (0..bits).rev() {
+        let bit = (scalar[i / 64] >> (i % 64)) & 1;
+        let swap = unsafe{::core::mem::transmute::<u8,bool>((bit ^ prev) as u8)};
+        ct_swap_slice(swap, &mut r0[..], &mut r1[..]);
+        prev = bit;
+        let sum = ops.add(r0, r1);
+        let dbl = ops.double(r0);
+        *r1 = sum;
+        *r0 = dbl;
+    }
+    let swap = unsafe{::core::mem::transmute::<u8,bool>(prev as u8)};
+    ct_swap_slice(swap, &mut r0[..], &mut r1[..]);
+}
+
+#[cfg(test)]
+struct Wrapping;
+#[cfg(test)]
+impl LadderOps<1> for Wrapping {
+    fn double(&self, p: &[u64;1]) -> [u64;1] {
+        [p[0].wrapping_add(p[0])]
+    }
+    fn add(&self, p: &[u64;1], q: &[u64;1]) -> [u64;1] {
+        [p[0].wrapping_add(q[0])]
+    }
+}
+#[test]
+fn test_ct_ladder() {
+    //the additive group mod 2^64, k * P is a multiplication
+    let scalars: [u64;6] = [0, 1, 2, 3, 0xDEADBEEF, u64::MAX];
+    for &k in scalars.iter() {
+        let mut r0 = [0u64];
+        let mut r1 = [7u64];
+        ct_ladder(&Wrapping, &mut r0, &mut r1, &[k], 64);
+        assert_eq!( r0[0], k.wrapping_mul(7));
+        assert_eq!( r1[0], k.wrapping_add(1).wrapping_mul(7));
+    }
+    let mut r0 = [0u64];
+    let mut r1 = [7u64];
+    ct_ladder(&Wrapping, &mut r0, &mut r1, &[0xFF], 4);
+    assert_eq!( r0[0], 0xF * 7);
+}
+#[test]
+#[should_panic]
+fn test_ct_ladder_panic() {
+    ct_ladder(&Wrapping, &mut [0u64], &mut [1u64], &[1], 65);
+}
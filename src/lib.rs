@@ -44,12 +44,48 @@
 //!I am reasonably confident it provides the advertised guarantees.
 
 #![feature(concat_idents)]
+#![cfg_attr(feature = "simd", feature(portable_simd))]
 
 #![no_std]
+#[cfg(feature = "rayon")]
+extern crate std;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+#[cfg(feature = "alloc")]
+extern crate alloc;
 use core::mem::transmute as trans;
+use core::cmp::Ordering;
 
 macro_rules! max { ($t:ident) => { ::core::$t::MAX } }
 
+pub mod arith;
+pub mod limbs;
+pub mod montgomery;
+pub mod bigint;
+pub mod ladder;
+pub mod aes;
+pub mod bitslice;
+pub mod crc;
+pub mod hex;
+pub mod base64;
+pub mod base32;
+pub mod bech32;
+pub mod endian;
+pub mod padding;
+pub mod der;
+pub mod table;
+pub mod sort;
+pub mod bitvec;
+mod arch;
+#[cfg(feature = "rayon")]
+pub mod parallel;
+#[cfg(feature = "bench")]
+pub mod bench;
+#[cfg(feature = "simd")]
+pub mod simd;
+#[cfg(feature = "alloc")]
+pub mod pem;
+
 
 /*
  * Rust booleans are effectively u8's with typing sugar.
@@ -114,6 +150,9 @@ pub trait ConstantTime : Sized {
     fn ct_eq_slice(x: &[Self], y: &[Self]) -> bool;
     fn ct_select(flag: bool, x: Self, y: Self) -> Self;
     fn ct_copy(flag: bool, x: &mut [Self], y: &[Self]);
+    fn ct_cmp_slice(x: &[Self], y: &[Self]) -> Ordering;
+    fn ct_all_zero(x: &[Self]) -> bool;
+    fn ct_lt(x: Self, y: Self) -> bool;
 }
 pub fn ct_eq<T>(x: T, y: T) -> bool
   where T: ConstantTime {
@@ -131,6 +170,18 @@ pub fn ct_copy<T>(flag: bool, x: &mut [T], y: &[T])
   where T: ConstantTime {
     <T as ConstantTime>::ct_copy(flag,x,y);
 }
+pub fn ct_cmp_slice<T>(x: &[T], y: &[T]) -> Ordering
+  where T: ConstantTime {
+    <T as ConstantTime>::ct_cmp_slice(x,y)
+}
+pub fn ct_all_zero<T>(x: &[T]) -> bool
+  where T: ConstantTime {
+    <T as ConstantTime>::ct_all_zero(x)
+}
+pub fn ct_lt<T>(x: T, y: T) -> bool
+  where T: ConstantTime {
+    <T as ConstantTime>::ct_lt(x,y)
+}
 
 macro_rules! impl_ConstantTime {
     ($code: ident) => {
@@ -147,6 +198,15 @@ macro_rules! impl_ConstantTime {
             fn ct_copy(flag: bool, x: &mut [$code], y: &[$code]) {
                 concat_idents!(ct_copy_,$code)(flag,x,y) 
             }
+            fn ct_cmp_slice( x: &[$code], y: &[$code]) -> Ordering {
+                concat_idents!(ct_,$code,_slice_cmp)(x,y) 
+            }
+            fn ct_all_zero( x: &[$code]) -> bool {
+                concat_idents!(ct_,$code,_all_zero)(x) 
+            }
+            fn ct_lt( x: $code, y: $code) -> bool {
+                concat_idents!(ct_,$code,_lt)(x,y) 
+            }
         }
     }
 }
@@ -158,6 +218,29 @@ impl_ConstantTime!(u64);
 impl_ConstantTime!(usize);
 
 
+/*
+ * The scalar primitives are #[inline(never)] so LLVM can not see
+ * through them at the call site and, for instance, turn a select
+ * on a known flag back into a branch.
+ *
+ * With the `inline` feature they become #[inline] instead, and the
+ * operands are passed through this barrier. They also lose
+ * #[no_mangle], an exported symbol is never inlined, so the C
+ * symbols are only there without the feature. A volatile read forces
+ * the value to be materialized and forgets everything LLVM knew
+ * about it, so the arithmetic that follows can not be specialized.
+ * Without the feature it is the identity, `cfg!` is resolved at
+ * compile time.
+ */
+#[inline(always)]
+fn barrier<T: Copy>(x: T) -> T {
+    if cfg!(feature = "inline") {
+        unsafe{::core::ptr::read_volatile(&x)}
+    } else {
+        x
+    }
+}
+
 /*
  * The purpose of the below macro is two fold. 
  *  1. Define the function to do constant unsigned integer comparisons
@@ -170,10 +253,11 @@ macro_rules! ct_eq_gen {
         ///Tests if two values are equal in constant time.
         ///
         ///Completely avoids branching.
-        #[no_mangle]
-        #[inline(never)]
+        #[cfg_attr(not(feature = "inline"), no_mangle)]
+        #[cfg_attr(not(feature = "inline"), inline(never))]
+        #[cfg_attr(feature = "inline", inline)]
         pub extern "C" fn $name( x: $code, y: $code) -> bool {
-            let mut z: $code = max!($code) ^ (x^y);
+            let mut z: $code = max!($code) ^ (barrier(x)^barrier(y));
             $(
                 z &= z.wrapping_shr($shr);
             )*
@@ -249,8 +333,13 @@ macro_rules! ct_eq_slice_gen {
                return false;
             }
             let mut flag: $code = 0;
-            for i in 0..x_len {
-                flag |= x[i] ^ y[i];
+            /*
+             * zip rather than indexing, the lengths are already known
+             * to be equal so there is no bounds check (and no panic
+             * branch) left inside of the loop body.
+             */
+            for (x_val, y_val) in x.iter().zip(y.iter()) {
+                flag |= x_val ^ y_val;
             }
             <$code as ConstantTime>::ct_eq(flag,0)
         }
@@ -269,7 +358,7 @@ macro_rules! ct_eq_slice_gen {
         }
     }
 }
-ct_eq_slice_gen!(ct_u8_slice_eq,u8;;
+ct_eq_slice_gen!(ct_u8_slice_eq_bytewise,u8;;
     test_ct_u8_slice_eq);
 ct_eq_slice_gen!(ct_u16_slice_eq,u16;;
     test_ct_u16_slice_eq);
@@ -280,6 +369,70 @@ ct_eq_slice_gen!(ct_u64_slice_eq,u64;;
 ct_eq_slice_gen!(ct_usize_slice_eq,usize;;
     test_ct_usize_slice_eq);
 
+///Check the equality of byte slices.
+///
+///Same guarantees as the other slice comparisons, but the bulk of the
+///slices is XOR'd a `usize` word at a time. The remaining tail bytes
+///are folded into the same accumulator, there is still exactly one
+///comparison at the end and no early exit.
+///
+///On wasm32 with the `wasm-simd` feature and `simd128` enabled the
+///bulk is XOR'd 16 bytes at a time instead.
+///
+///`ct_u8_slice_eq_bytewise` is the plain byte at a time version.
+#[no_mangle]
+pub extern "C" fn ct_u8_slice_eq( x: &[u8], y: &[u8]) -> bool {
+    let x_len = x.len();
+    let y_len = y.len();
+    if x_len != y_len {
+       return false;
+    }
+    let (done, vector_eq) = arch::eq_u8(x, y);
+    let (x, y) = (&x[done..], &y[done..]);
+    const WORD: usize = ::core::mem::size_of::<usize>();
+    let x_words = x.chunks_exact(WORD);
+    let y_words = y.chunks_exact(WORD);
+    let x_tail = x_words.remainder();
+    let y_tail = y_words.remainder();
+    let mut flag: usize = 0;
+    for (x_word, y_word) in x_words.zip(y_words) {
+        /*
+         * chunks_exact guarantees WORD bytes, the loads are unaligned
+         * since X and Y need not share an alignment.
+         */
+        let x_val = unsafe{::core::ptr::read_unaligned(x_word.as_ptr() as *const usize)};
+        let y_val = unsafe{::core::ptr::read_unaligned(y_word.as_ptr() as *const usize)};
+        flag |= x_val ^ y_val;
+    }
+    for (x_val, y_val) in x_tail.iter().zip(y_tail.iter()) {
+        flag |= (x_val ^ y_val) as usize;
+    }
+    ct_usize_eq(flag,0) & vector_eq
+}
+#[test]
+fn test_ct_u8_slice_eq_words() {
+    let mut x: [u8;40] = [0;40];
+    for i in 0..40 {
+        x[i] = (i * 7) as u8;
+    }
+    for start in 0..3 {
+        for len in 0..(40 - start) {
+            let mut y = x;
+            let a = &x[start..(start + len)];
+            assert_eq!( ct_u8_slice_eq(a, &y[start..(start + len)]), true);
+            assert_eq!( ct_u8_slice_eq(a, a), ct_u8_slice_eq_bytewise(a, a));
+            for i in start..(start + len) {
+                y[i] ^= 0x80;
+                let b = &y[start..(start + len)];
+                assert_eq!( ct_u8_slice_eq(a, b), false);
+                assert_eq!( ct_u8_slice_eq_bytewise(a, b), false);
+                y[i] ^= 0x80;
+            }
+        }
+    }
+    assert_eq!( ct_u8_slice_eq(&x[..8], &x[..9]), false);
+}
+
 
 macro_rules! ct_select_gen {
     ($name:ident,$code:ident;;$test_name:ident,$v0:expr,$v1:expr) => {
@@ -298,10 +451,11 @@ macro_rules! ct_select_gen {
         ///random state of our machine + quantum winds.
         ///
         ///This should provide a consistent guarantee of speed.
-        #[no_mangle]
-        #[inline(never)]
+        #[cfg_attr(not(feature = "inline"), no_mangle)]
+        #[cfg_attr(not(feature = "inline"), inline(never))]
+        #[cfg_attr(feature = "inline", inline)]
         pub extern "C" fn $name(flag: bool, x: $code, y: $code) -> $code {
-            let val: u8 = unsafe{trans::<bool,u8>(flag)};
+            let val: u8 = barrier(unsafe{trans::<bool,u8>(flag)});
             let flag = val as $code;
             ((max!($code) ^ flag.wrapping_sub(1))&x)|(flag.wrapping_sub(1)&y)
         }
@@ -335,6 +489,61 @@ ct_select_gen!(ct_select_u64,u64;;
 ct_select_gen!(ct_select_usize,usize;;
     test_ct_select_usize,155,4);
 
+/*
+ * Unsigned less-than. Slice operations use it to build masks out of
+ * (secret) lengths and indexes without ever writing `x < y`, which
+ * LLVM is free to turn into a branch.
+ *
+ * The borrow of x - y is recovered from the top bit
+ *
+ *    ((!x & y) | ((!x | y) & (x - y))) >> (BITS - 1)
+ *
+ * See Hacker's Delight 2-12.
+ */
+macro_rules! ct_lt_gen {
+    ($name:ident,$code:ident;;$test_name:ident,$v0:expr,$v1:expr) => {
+        ///Tests if X is less than Y in constant time.
+        ///
+        ///Completely avoids branching.
+        #[cfg_attr(not(feature = "inline"), no_mangle)]
+        #[cfg_attr(not(feature = "inline"), inline(never))]
+        #[cfg_attr(feature = "inline", inline)]
+        pub extern "C" fn $name(x: $code, y: $code) -> bool {
+            let x = barrier(x);
+            let y = barrier(y);
+            let shift = (::core::mem::size_of::<$code>() * 8) - 1;
+            let borrow = ((!x & y) | ((!x | y) & x.wrapping_sub(y))) >> shift;
+            let val = borrow as u8;
+            unsafe{trans::<u8,bool>(val)}
+        }
+        #[test]
+        fn $test_name() {
+            const MAX: $code = max!($code);
+            assert_eq!( $name(0,0), false);
+            assert_eq!( $name(0,1), true);
+            assert_eq!( $name(1,0), false);
+            let x: $code = $v0;
+            let y: $code = $v1;
+            assert_eq!( $name(x,y), x < y);
+            assert_eq!( $name(y,x), y < x);
+            assert_eq!( $name(0,MAX), true);
+            assert_eq!( $name(MAX,0), false);
+            assert_eq!( $name(MAX,MAX), false);
+            assert_eq!( $name(MAX-1,MAX), true);
+        }
+    }
+}
+ct_lt_gen!(ct_u8_lt,u8;;
+    test_ct_u8_lt,155,4);
+ct_lt_gen!(ct_u16_lt,u16;;
+    test_ct_u16_lt,30597,4);
+ct_lt_gen!(ct_u32_lt,u32;;
+    test_ct_u32_lt,0x0DD74AA2,4);
+ct_lt_gen!(ct_u64_lt,u64;;
+    test_ct_u64_lt,25893654215879,2);
+ct_lt_gen!(ct_usize_lt,usize;;
+    test_ct_usize_lt,155,4);
+
 macro_rules! ct_constant_copy_gen {
     ($name:ident,$code:ident
     ;;$test_name:ident,$sl_eq:ident,$other_test:ident) => {
@@ -354,10 +563,9 @@ macro_rules! ct_constant_copy_gen {
             if x_len != y_len {
                 panic!("Consistent Time: Attempted to copy between non-equal lens");
             }
-            for i in 0..x_len {
-                let y_temp = y[i].clone();
-                let x_temp = x[i].clone();
-                x[i] = <$code as ConstantTime>::ct_select(flag,y_temp,x_temp);
+            //zip avoids a bounds check inside of the loop, see above
+            for (x_val, y_val) in x.iter_mut().zip(y.iter()) {
+                *x_val = <$code as ConstantTime>::ct_select(flag,*y_val,*x_val);
             }
         }
         #[test]
@@ -384,7 +592,7 @@ macro_rules! ct_constant_copy_gen {
         }
     }
 }
-ct_constant_copy_gen!(ct_copy_u8,u8;;
+ct_constant_copy_gen!(ct_copy_u8_bytewise,u8;;
     test_ct_copy_u8,ct_u8_slice_eq,test_ct_copy_u8_panic);
 ct_constant_copy_gen!(ct_copy_u16,u16;;
     test_ct_copy_u16,ct_u16_slice_eq,test_ct_copy_u16_panic);
@@ -394,3 +602,1780 @@ ct_constant_copy_gen!(ct_copy_u64,u64;;
     test_ct_copy_u64,ct_u64_slice_eq,test_ct_copy_u64_panic);
 ct_constant_copy_gen!(ct_copy_usize,usize;;
     test_ct_copy_usize,ct_usize_slice_eq,test_ct_copy_usize_panic);
+
+///Optional buffer copying
+///
+///IF flag == True THEN X will be set to Y
+///
+///If flag == False THEN X is unchanged
+///
+///When compiled with AVX2 (x86_64), NEON (aarch64) or, with the
+///`wasm-simd` feature, SIMD128 (wasm32) enabled the bulk of the buffer
+///is blended a vector at a time under a splatted mask.
+///The tail, and every other target, uses the same masking a byte at a
+///time. `ct_copy_u8_bytewise` is the portable version.
+///
+///#Panic:
+///
+///This function will panic if X and Y are not equal length. 
+#[no_mangle]
+pub extern "C" fn ct_copy_u8(flag: bool, x: &mut [u8], y: &[u8]) {
+    let x_len = x.len();
+    let y_len = y.len();
+    if x_len != y_len {
+        panic!("Consistent Time: Attempted to copy between non-equal lens");
+    }
+    let mask = ct_select_u8(flag, 0xFF, 0);
+    let done = arch::copy_u8(mask, x, y);
+    for (x_val, y_val) in x[done..].iter_mut().zip(y[done..].iter()) {
+        *x_val = (y_val & mask) | (*x_val & !mask);
+    }
+}
+#[test]
+fn test_ct_copy_u8_vector() {
+    let mut y: [u8;100] = [0;100];
+    for i in 0..100 {
+        y[i] = (i * 5 + 1) as u8;
+    }
+    for len in 0..100 {
+        let mut x: [u8;100] = [0;100];
+        ct_copy_u8(false, &mut x[..len], &y[..len]);
+        assert_eq!( &x[..], &[0;100][..]);
+        ct_copy_u8(true, &mut x[..len], &y[..len]);
+        assert_eq!( &x[..len], &y[..len]);
+        assert_eq!( ct_all_zero(&x[len..]), true);
+    }
+}
+
+///Optional copying of many buffers under one flag.
+///
+///IF flag == True THEN every `dst` will be set to its `src`
+///
+///If flag == False THEN every `dst` is unchanged
+///
+///Every pair is transversed reguardless of the flag, so replacing
+///several secrets at once never requires an `if` around `ct_copy`.
+///
+///#Panic:
+///
+///This function will panic if any `(dst, src)` pair is not of equal
+///length. All lengths are checked before anything is copied.
+pub fn ct_copy_many<T>(flag: bool, pairs: &mut [(&mut [T], &[T])])
+  where T: ConstantTime {
+    for pair in pairs.iter() {
+        if pair.0.len() != pair.1.len() {
+            panic!("Consistent Time: Attempted to copy between non-equal lens");
+        }
+    }
+    for pair in pairs.iter_mut() {
+        <T as ConstantTime>::ct_copy(flag, &mut *pair.0, pair.1);
+    }
+}
+#[test]
+fn test_ct_copy_many() {
+    let mut a: [u8;4] = [0,0,0,0];
+    let mut b: [u8;2] = [1,1];
+    let src_a: [u8;4] = [9,8,7,6];
+    let src_b: [u8;2] = [5,4];
+    ct_copy_many(false, &mut [(&mut a[..], &src_a[..]), (&mut b[..], &src_b[..])]);
+    assert_eq!( a, [0,0,0,0]);
+    assert_eq!( b, [1,1]);
+    ct_copy_many(true, &mut [(&mut a[..], &src_a[..]), (&mut b[..], &src_b[..])]);
+    assert_eq!( a, src_a);
+    assert_eq!( b, src_b);
+}
+#[test]
+#[should_panic]
+fn test_ct_copy_many_panic() {
+    let mut a: [u64;4] = [0,0,0,0];
+    let mut b: [u64;2] = [1,1];
+    let src_a: [u64;4] = [9,8,7,6];
+    let src_b: [u64;3] = [5,4,3];
+    ct_copy_many(true, &mut [(&mut a[..], &src_a[..]), (&mut b[..], &src_b[..])]);
+}
+
+///Length hiding equality of a secret and caller input.
+///
+///Exactly `bucket_len` bytes are processed no matter how long `secret`
+///or `input` are. The length of the stored secret is folded into the
+///result instead of being checked up front, so the timing only reveals
+///the (public) bucket size.
+///
+///Positions past the end of a slice are read from index 0 and masked
+///out, the loop body is identical for every position.
+///
+///Returns `false` without scanning if `input` is longer than the bucket,
+///this only depends on the caller supplied input.
+///
+///#Panic:
+///
+///This function will panic if `secret` is longer than `bucket_len`.
+pub fn ct_eq_padded(secret: &[u8], input: &[u8], bucket_len: usize) -> bool {
+    let s_len = secret.len();
+    let i_len = input.len();
+    if s_len > bucket_len {
+        panic!("Consistent Time: Secret must fit its bucket");
+    }
+    if i_len > bucket_len {
+        return false;
+    }
+    //an empty secret or input still needs something to read from
+    let zero: [u8;1] = [0];
+    let secret = if s_len == 0 { &zero[..] } else { secret };
+    let input = if i_len == 0 { &zero[..] } else { input };
+    let mut flag: u8 = 0;
+    for i in 0..bucket_len {
+        let s_in = ct_usize_lt(i, s_len);
+        let i_in = ct_usize_lt(i, i_len);
+        let s_val = secret[ct_select_usize(s_in, i, 0)] & ct_select_u8(s_in, 0xFF, 0);
+        let i_val = input[ct_select_usize(i_in, i, 0)] & ct_select_u8(i_in, 0xFF, 0);
+        flag |= s_val ^ i_val;
+    }
+    ct_u8_eq(flag, 0) & ct_usize_eq(s_len, i_len)
+}
+#[test]
+fn test_ct_eq_padded() {
+    let secret = b"hunter2";
+    assert_eq!( ct_eq_padded(secret, b"hunter2", 32), true);
+    assert_eq!( ct_eq_padded(secret, b"hunter2", 7), true);
+    assert_eq!( ct_eq_padded(secret, b"hunter3", 32), false);
+    assert_eq!( ct_eq_padded(secret, b"hunter", 32), false);
+    assert_eq!( ct_eq_padded(secret, b"hunter2\0", 32), false);
+    assert_eq!( ct_eq_padded(secret, b"", 32), false);
+    assert_eq!( ct_eq_padded(secret, b"hunter2hunter2", 8), false);
+    assert_eq!( ct_eq_padded(b"\0\0", b"\0", 8), false);
+    //an empty secret only matches an empty input
+    assert_eq!( ct_eq_padded(b"", b"", 8), true);
+    assert_eq!( ct_eq_padded(b"", b"\0", 8), false);
+    assert_eq!( ct_eq_padded(b"", b"hunter2", 8), false);
+    assert_eq!( ct_eq_padded(b"", b"", 0), true);
+}
+#[test]
+#[should_panic]
+fn test_ct_eq_padded_panic() {
+    ct_eq_padded(b"hunter2", b"hunter2", 4);
+}
+
+///Equality of two secrets stored in fixed capacity buffers.
+///
+///`x_len` and `y_len` are the (secret) number of used elements in
+///`x` and `y`. The full capacity is always transversed, bytes past
+///either length are masked out, and the length comparison is folded
+///into the result. There is no early return when the lengths differ,
+///unlike `ct_eq_slice`.
+///
+///A length larger than the capacity makes the result `false`.
+///
+///#Panic:
+///
+///This function will panic if `x` and `y` do not have equal capacity.
+pub fn ct_eq_bounded(x: &[u8], x_len: usize, y: &[u8], y_len: usize) -> bool {
+    let cap = x.len();
+    if cap != y.len() {
+        panic!("Consistent Time: Attempted to compare non-equal capacities");
+    }
+    let mut flag: u8 = 0;
+    for i in 0..cap {
+        let x_val = x[i] & ct_select_u8(ct_usize_lt(i, x_len), 0xFF, 0);
+        let y_val = y[i] & ct_select_u8(ct_usize_lt(i, y_len), 0xFF, 0);
+        flag |= x_val ^ y_val;
+    }
+    let fits = !ct_usize_lt(cap, x_len);
+    ct_u8_eq(flag, 0) & ct_usize_eq(x_len, y_len) & fits
+}
+#[test]
+fn test_ct_eq_bounded() {
+    let x: [u8;8] = [1,2,3,4,0xAA,0xAA,0xAA,0xAA];
+    let y: [u8;8] = [1,2,3,4,0x55,0x55,0x55,0x55];
+    assert_eq!( ct_eq_bounded(&x, 4, &y, 4), true);
+    assert_eq!( ct_eq_bounded(&x, 0, &y, 0), true);
+    assert_eq!( ct_eq_bounded(&x, 5, &y, 5), false);
+    assert_eq!( ct_eq_bounded(&x, 4, &y, 3), false);
+    assert_eq!( ct_eq_bounded(&x, 8, &x, 8), true);
+    assert_eq!( ct_eq_bounded(&x, 9, &x, 9), false);
+    assert_eq!( ct_eq_bounded(&[], 0, &[], 0), true);
+}
+#[test]
+#[should_panic]
+fn test_ct_eq_bounded_panic() {
+    let x: [u8;8] = [0;8];
+    let y: [u8;7] = [0;7];
+    ct_eq_bounded(&x, 4, &y, 4);
+}
+
+/*
+ * `Ordering` is `#[repr(i8)]` with Less = -1, Equal = 0, Greater = 1.
+ * Just like `bool` it can be built from its integer representation
+ * without a branch, here via `u8` so two's complement is explicit:
+ *
+ *    Less    = 0xFF
+ *    Equal   = 0x00
+ *    Greater = 0x01
+ */
+macro_rules! ct_cmp_slice_gen {
+    ($name:ident,$code:ident,$lt:ident;;$test_name:ident) => {
+        ///Lexicographic comparison of slices.
+        ///
+        ///Every element of the common prefix is compared, the first
+        ///difference is remembered with masks rather than returned
+        ///early. If the prefix is equal the (public) lengths decide,
+        ///the shorter slice being `Less`.
+        #[no_mangle]
+        pub extern "C" fn $name( x: &[$code], y: &[$code]) -> Ordering {
+            let x_len = x.len();
+            let y_len = y.len();
+            let len = if x_len < y_len { x_len } else { y_len };
+            let mut res: u8 = 0;
+            for i in 0..len {
+                let lt = unsafe{trans::<bool,u8>($lt(x[i],y[i]))};
+                let gt = unsafe{trans::<bool,u8>($lt(y[i],x[i]))};
+                res = ct_select_u8(ct_u8_eq(res,0), gt.wrapping_sub(lt), res);
+            }
+            let lt = unsafe{trans::<bool,u8>(ct_usize_lt(x_len,y_len))};
+            let gt = unsafe{trans::<bool,u8>(ct_usize_lt(y_len,x_len))};
+            res = ct_select_u8(ct_u8_eq(res,0), gt.wrapping_sub(lt), res);
+            unsafe{trans::<i8,Ordering>(res as i8)}
+        }
+        #[test]
+        fn $test_name() {
+            const MAX: $code = max!($code);
+            let x: [$code;4] = [0,1,2,3];
+            let y: [$code;4] = [0,1,3,0];
+            let z: [$code;4] = [MAX,0,0,0];
+            assert_eq!( ct_cmp_slice(&x, &x), Ordering::Equal);
+            assert_eq!( ct_cmp_slice(&x, &y), Ordering::Less);
+            assert_eq!( ct_cmp_slice(&y, &x), Ordering::Greater);
+            assert_eq!( ct_cmp_slice(&z, &x), Ordering::Greater);
+            assert_eq!( ct_cmp_slice(&x, &z), Ordering::Less);
+            assert_eq!( ct_cmp_slice(&x[..3], &x), Ordering::Less);
+            assert_eq!( ct_cmp_slice(&x, &x[..3]), Ordering::Greater);
+            assert_eq!( ct_cmp_slice(&y, &x[..3]), Ordering::Greater);
+            assert_eq!( ct_cmp_slice::<$code>(&[], &[]), Ordering::Equal);
+        }
+    }
+}
+ct_cmp_slice_gen!(ct_u8_slice_cmp,u8,ct_u8_lt;;
+    test_ct_u8_slice_cmp);
+ct_cmp_slice_gen!(ct_u16_slice_cmp,u16,ct_u16_lt;;
+    test_ct_u16_slice_cmp);
+ct_cmp_slice_gen!(ct_u32_slice_cmp,u32,ct_u32_lt;;
+    test_ct_u32_slice_cmp);
+ct_cmp_slice_gen!(ct_u64_slice_cmp,u64,ct_u64_lt;;
+    test_ct_u64_slice_cmp);
+ct_cmp_slice_gen!(ct_usize_slice_cmp,usize,ct_usize_lt;;
+    test_ct_usize_slice_cmp);
+
+///Compare two byte strings as big endian unsigned integers.
+///
+///This is a single subtraction from the least significant byte up,
+///the borrow out says `Less` and any differing byte says not `Equal`.
+///Nothing is selected per byte.
+///
+///#Panic:
+///
+///This function will panic if the slices are not equal length.
+pub fn ct_cmp_be_bytes(x: &[u8], y: &[u8]) -> Ordering {
+    if x.len() != y.len() {
+        panic!("Consistent Time: Attempted big endian compare between non-equal lens");
+    }
+    let mut borrow: u16 = 0;
+    let mut diff: u8 = 0;
+    for (x_val, y_val) in x.iter().rev().zip(y.iter().rev()) {
+        let t = (*x_val as u16).wrapping_sub(*y_val as u16).wrapping_sub(borrow);
+        borrow = (t >> 8) & 1;
+        diff |= x_val ^ y_val;
+    }
+    let lt = borrow as u8;
+    let ne = unsafe{trans::<bool,u8>(!ct_u8_eq(diff, 0))};
+    let gt = ne & (lt ^ 1);
+    unsafe{trans::<i8,Ordering>(gt.wrapping_sub(lt) as i8)}
+}
+#[test]
+fn test_ct_cmp_be_bytes() {
+    let vals: [u32;7] = [0, 1, 0xFF, 0x100, 0x01000000, 0xFFFFFFFE, 0xFFFFFFFF];
+    for &x in vals.iter() {
+        for &y in vals.iter() {
+            let xb: [u8;4] = [(x >> 24) as u8, (x >> 16) as u8, (x >> 8) as u8, x as u8];
+            let yb: [u8;4] = [(y >> 24) as u8, (y >> 16) as u8, (y >> 8) as u8, y as u8];
+            assert_eq!( ct_cmp_be_bytes(&xb, &yb), x.cmp(&y));
+        }
+    }
+    assert_eq!( ct_cmp_be_bytes(&[], &[]), Ordering::Equal);
+}
+#[test]
+#[should_panic]
+fn test_ct_cmp_be_bytes_panic() {
+    ct_cmp_be_bytes(&[0;2], &[0;3]);
+}
+
+///Check if a big endian encoded scalar is canonical, below the modulus.
+///
+///Decoders of field elements and scalars must reject encodings that
+///are not reduced, or two encodings of the same value are accepted.
+///The comparison is `ct_cmp_be_bytes`, every byte of both slices is
+///read whatever the result.
+///
+///IF x < modulus THEN true
+///
+///If x >= modulus THEN false
+///
+///#Panic:
+///
+///This function will panic if the slices are not equal length.
+pub fn ct_is_canonical(x: &[u8], modulus: &[u8]) -> bool {
+    let ord = unsafe{trans::<Ordering,u8>(ct_cmp_be_bytes(x, modulus))};
+    ct_u8_eq(ord, 0xFF)
+}
+///Check if a big endian encoded scalar is canonical and non-zero, in
+///`1..modulus`.
+///
+///The zero check reads every byte as well.
+///
+///#Panic:
+///
+///This function will panic if the slices are not equal length.
+pub fn ct_is_canonical_nonzero(x: &[u8], modulus: &[u8]) -> bool {
+    let canonical = ct_is_canonical(x, modulus);
+    canonical & !ct_u8_all_zero(x)
+}
+#[test]
+fn test_ct_is_canonical() {
+    let m: [u8;3] = [0x10, 0x00, 0x01];
+    assert_eq!( ct_is_canonical(&[0x10, 0x00, 0x00], &m), true);
+    assert_eq!( ct_is_canonical(&[0x10, 0x00, 0x01], &m), false);
+    assert_eq!( ct_is_canonical(&[0x10, 0x00, 0x02], &m), false);
+    assert_eq!( ct_is_canonical(&[0xFF, 0x00, 0x00], &m), false);
+    assert_eq!( ct_is_canonical(&[0x00, 0x00, 0x00], &m), true);
+    assert_eq!( ct_is_canonical_nonzero(&[0x00, 0x00, 0x00], &m), false);
+    assert_eq!( ct_is_canonical_nonzero(&[0x00, 0x00, 0x01], &m), true);
+    assert_eq!( ct_is_canonical_nonzero(&[0x10, 0x00, 0x01], &m), false);
+}
+
+///Check if `x` begins with `prefix`.
+///
+///The full length of `prefix` is always compared, a mismatch in the
+///first element takes as long as one in the last.
+///
+///Returns `false` right away if `prefix` is longer than `x`, this only
+///depends on the lengths.
+pub fn ct_starts_with<T>(x: &[T], prefix: &[T]) -> bool
+  where T: ConstantTime {
+    let len = prefix.len();
+    if len > x.len() {
+        return false;
+    }
+    <T as ConstantTime>::ct_eq_slice(&x[..len], prefix)
+}
+///Check if `x` ends with `suffix`.
+///
+///The full length of `suffix` is always compared, a mismatch in the
+///first element takes as long as one in the last.
+///
+///Returns `false` right away if `suffix` is longer than `x`, this only
+///depends on the lengths.
+pub fn ct_ends_with<T>(x: &[T], suffix: &[T]) -> bool
+  where T: ConstantTime {
+    let len = suffix.len();
+    let x_len = x.len();
+    if len > x_len {
+        return false;
+    }
+    <T as ConstantTime>::ct_eq_slice(&x[(x_len - len)..], suffix)
+}
+#[test]
+fn test_ct_starts_with() {
+    let header = b"Bearer abcdef";
+    assert_eq!( ct_starts_with(&header[..], b"Bearer "), true);
+    assert_eq!( ct_starts_with(&header[..], b"bearer "), false);
+    assert_eq!( ct_starts_with(&header[..], b""), true);
+    assert_eq!( ct_starts_with(&header[..], &header[..]), true);
+    assert_eq!( ct_starts_with(&b"Bearer"[..], b"Bearer "), false);
+    let x: [u32;3] = [7,8,9];
+    assert_eq!( ct_starts_with(&x, &[7,8]), true);
+    assert_eq!( ct_starts_with(&x, &[8]), false);
+}
+#[test]
+fn test_ct_ends_with() {
+    let header = b"Bearer abcdef";
+    assert_eq!( ct_ends_with(&header[..], b"abcdef"), true);
+    assert_eq!( ct_ends_with(&header[..], b"abcdeF"), false);
+    assert_eq!( ct_ends_with(&header[..], b""), true);
+    assert_eq!( ct_ends_with(&header[..], &header[..]), true);
+    assert_eq!( ct_ends_with(&b"cdef"[..], b"abcdef"), false);
+    let x: [u32;3] = [7,8,9];
+    assert_eq!( ct_ends_with(&x, &[8,9]), true);
+    assert_eq!( ct_ends_with(&x, &[8]), false);
+}
+
+///Constant time substring search.
+///
+///Every alignment of `needle` inside `x` is evaluated and every
+///element is compared at every alignment. Returns if `needle` was
+///found, and the index of its first occurence (or `0` if it was not
+///found). The index is accumulated with masks so neither the result
+///nor the position can be learned from the timing.
+///
+///An empty `needle` is found at index `0`. A `needle` longer than `x`
+///is never found, this is decided by the lengths alone.
+pub fn ct_find<T>(x: &[T], needle: &[T]) -> (bool, usize)
+  where T: ConstantTime {
+    let len = needle.len();
+    let x_len = x.len();
+    if len > x_len {
+        return (false, 0);
+    }
+    let mut found = false;
+    let mut index: usize = 0;
+    for i in 0..(x_len - len + 1) {
+        let hit = <T as ConstantTime>::ct_eq_slice(&x[i..(i + len)], needle);
+        index = ct_select_usize(hit & !found, i, index);
+        found |= hit;
+    }
+    (found, index)
+}
+///Check if `needle` occurs inside of `x`.
+///
+///See `ct_find`, every alignment is always compared.
+pub fn ct_contains<T>(x: &[T], needle: &[T]) -> bool
+  where T: ConstantTime {
+    ct_find(x, needle).0
+}
+#[test]
+fn test_ct_find() {
+    let data = b"\x01\x02padding\x00\x00secret\x00\x00";
+    assert_eq!( ct_find(&data[..], b"\x00\x00"), (true, 9));
+    assert_eq!( ct_find(&data[..], b"secret"), (true, 11));
+    assert_eq!( ct_find(&data[..], b"\x01\x02"), (true, 0));
+    assert_eq!( ct_find(&data[..], b"\x00\x00\x00"), (false, 0));
+    assert_eq!( ct_find(&data[..], b""), (true, 0));
+    assert_eq!( ct_find(&b"ab"[..], b"abc"), (false, 0));
+    let x: [u16;5] = [1,2,3,2,3];
+    assert_eq!( ct_find(&x, &[2,3]), (true, 1));
+    assert_eq!( ct_find(&x, &[3]), (true, 2));
+}
+#[test]
+fn test_ct_contains() {
+    let data = b"\x01\x02padding\x00\x00secret\x00\x00";
+    assert_eq!( ct_contains(&data[..], b"secret"), true);
+    assert_eq!( ct_contains(&data[..], b"Secret"), false);
+    assert_eq!( ct_contains(&data[..], &data[..]), true);
+}
+
+///Find the first occurence of `value` in `x`.
+///
+///The whole slice is always scanned. Returns if `value` was found,
+///and the index of its first occurence (or `0` if it was not found).
+///The index is accumulated with masks, so locating a separator inside
+///of decrypted data does not reveal where it is.
+pub fn ct_index_of<T>(x: &[T], value: T) -> (bool, usize)
+  where T: ConstantTime + Copy {
+    let mut found = false;
+    let mut index: usize = 0;
+    for i in 0..x.len() {
+        let hit = <T as ConstantTime>::ct_eq(x[i], value);
+        index = ct_select_usize(hit & !found, i, index);
+        found |= hit;
+    }
+    (found, index)
+}
+#[test]
+fn test_ct_index_of() {
+    let block: [u8;8] = [0x00,0x02,0xAA,0xBB,0x00,0xCC,0x00,0xDD];
+    assert_eq!( ct_index_of(&block[1..], 0x00), (true, 3));
+    assert_eq!( ct_index_of(&block, 0x00), (true, 0));
+    assert_eq!( ct_index_of(&block, 0xDD), (true, 7));
+    assert_eq!( ct_index_of(&block, 0xEE), (false, 0));
+    assert_eq!( ct_index_of::<u8>(&[], 0x00), (false, 0));
+    let x: [u64;3] = [5,max!(u64),5];
+    assert_eq!( ct_index_of(&x, max!(u64)), (true, 1));
+}
+
+///Find which of `candidates` equals `needle`.
+///
+///`needle` is compared against every candidate with `ct_eq_slice`,
+///there is no early exit on a match, and the index of the first match
+///is accumulated with masks. Returns if there was a match and its
+///index (or `0` if there was not). Looking up a key by a secret key
+///ID does not reveal which entry it was.
+///
+///Candidates whose length differs from `needle` are rejected by
+///length alone, keep the lengths uniform if they are secret.
+pub fn ct_match_index<T>(needle: &[T], candidates: &[&[T]]) -> (bool, usize)
+  where T: ConstantTime {
+    let mut found = false;
+    let mut index: usize = 0;
+    for (i, c) in candidates.iter().enumerate() {
+        let hit = <T as ConstantTime>::ct_eq_slice(needle, c);
+        index = ct_select_usize(hit & !found, i, index);
+        found |= hit;
+    }
+    (found, index)
+}
+#[test]
+fn test_ct_match_index() {
+    let ids: [&[u8];4] = [b"key-a", b"key-b", b"key-c", b"key-b"];
+    assert_eq!( ct_match_index(b"key-a", &ids), (true, 0));
+    assert_eq!( ct_match_index(b"key-b", &ids), (true, 1));
+    assert_eq!( ct_match_index(b"key-c", &ids), (true, 2));
+    assert_eq!( ct_match_index(b"key-d", &ids), (false, 0));
+    assert_eq!( ct_match_index(b"key", &ids), (false, 0));
+    assert_eq!( ct_match_index::<u8>(b"key-a", &[]), (false, 0));
+}
+
+///Oblivious read at a secret index.
+///
+///Every element of `x` is read and the one at `index` is kept with
+///`ct_select`. The memory access pattern is the same for every index,
+///so secret indexed table lookups (S-boxes) do not leak through the
+///cache or branch predictor.
+///
+///An out of range `index` selects nothing and returns `x[0]`, there is
+///no bounds check on the secret.
+///
+///#Panic:
+///
+///This function will panic if `x` is empty.
+pub fn ct_lookup<T>(x: &[T], index: usize) -> T
+  where T: ConstantTime + Copy {
+    if x.is_empty() {
+        panic!("Consistent Time: Attempted lookup in an empty slice");
+    }
+    let mut val = x[0];
+    for i in 0..x.len() {
+        val = <T as ConstantTime>::ct_select(ct_usize_eq(i, index), x[i], val);
+    }
+    val
+}
+#[test]
+fn test_ct_lookup() {
+    let sbox: [u8;8] = [0x63,0x7C,0x77,0x7B,0xF2,0x6B,0x6F,0xC5];
+    for i in 0..8 {
+        assert_eq!( ct_lookup(&sbox, i), sbox[i]);
+    }
+    assert_eq!( ct_lookup(&sbox, 8), sbox[0]);
+    let x: [u32;3] = [0xDEADBEEF,max!(u32),0];
+    assert_eq!( ct_lookup(&x, 1), max!(u32));
+    assert_eq!( ct_lookup(&x, 2), 0);
+}
+#[test]
+#[should_panic]
+fn test_ct_lookup_panic() {
+    ct_lookup::<u8>(&[], 0);
+}
+
+///Oblivious write at a secret index.
+///
+///Every element of `x` is rewritten, the one at `index` with `value`
+///and all others with themselves. The memory access pattern is the
+///same for every index.
+///
+///An out of range `index` leaves `x` unchanged, there is no bounds
+///check on the secret.
+pub fn ct_store<T>(x: &mut [T], index: usize, value: T)
+  where T: ConstantTime + Copy {
+    for i in 0..x.len() {
+        x[i] = <T as ConstantTime>::ct_select(ct_usize_eq(i, index), value, x[i]);
+    }
+}
+#[test]
+fn test_ct_store() {
+    let mut x: [u8;4] = [0,0,0,0];
+    ct_store(&mut x, 2, 0xAA);
+    assert_eq!( x, [0,0,0xAA,0]);
+    ct_store(&mut x, 0, 0xBB);
+    assert_eq!( x, [0xBB,0,0xAA,0]);
+    ct_store(&mut x, 4, 0xCC);
+    assert_eq!( x, [0xBB,0,0xAA,0]);
+    let mut y: [usize;2] = [1,2];
+    ct_store(&mut y, 1, max!(usize));
+    assert_eq!( y, [1,max!(usize)]);
+}
+
+///Count how many positions of two slices are equal.
+///
+///Every position is compared and the count is accumulated from the
+///comparison bits, there is no early exit and no branch on a match.
+///
+///#Panic:
+///
+///This function will panic if X and Y are not equal length.
+pub fn ct_count_eq<T>(x: &[T], y: &[T]) -> usize
+  where T: ConstantTime + Copy {
+    let len = x.len();
+    if len != y.len() {
+        panic!("Consistent Time: Attempted to count between non-equal lens");
+    }
+    let mut count: usize = 0;
+    for i in 0..len {
+        let hit: u8 = unsafe{trans::<bool,u8>(<T as ConstantTime>::ct_eq(x[i], y[i]))};
+        count += hit as usize;
+    }
+    count
+}
+#[test]
+fn test_ct_count_eq() {
+    let x: [u8;6] = [1,2,3,4,5,6];
+    let y: [u8;6] = [1,0,3,0,5,0];
+    assert_eq!( ct_count_eq(&x, &x), 6);
+    assert_eq!( ct_count_eq(&x, &y), 3);
+    assert_eq!( ct_count_eq(&x, &[0;6]), 0);
+    assert_eq!( ct_count_eq::<u64>(&[], &[]), 0);
+}
+#[test]
+#[should_panic]
+fn test_ct_count_eq_panic() {
+    ct_count_eq::<u16>(&[1,2], &[1]);
+}
+
+/*
+ * Branch free population count of a byte. This is the classic
+ * SWAR reduction, summing neighbouring 1, 2 then 4 bit fields.
+ * No lookup tables and no dependence on a popcnt instruction.
+ */
+fn popcount_u8(x: u8) -> u8 {
+    let x = x - ((x >> 1) & 0x55);
+    let x = (x & 0x33) + ((x >> 2) & 0x33);
+    (x + (x >> 4)) & 0x0F
+}
+#[test]
+fn test_popcount_u8() {
+    for i in 0..256 {
+        let x = i as u8;
+        assert_eq!( popcount_u8(x) as u32, x.count_ones());
+    }
+}
+
+///Number of set bits in a slice.
+///
+///Every byte is counted with the same shift and mask sequence, the
+///result never influences the control flow.
+pub fn ct_hamming_weight(x: &[u8]) -> usize {
+    let mut count: usize = 0;
+    for i in 0..x.len() {
+        count += popcount_u8(x[i]) as usize;
+    }
+    count
+}
+///Number of differing bits between two slices.
+///
+///Every byte is compared, see `ct_hamming_weight`.
+///
+///#Panic:
+///
+///This function will panic if X and Y are not equal length.
+pub fn ct_hamming_distance(x: &[u8], y: &[u8]) -> usize {
+    let len = x.len();
+    if len != y.len() {
+        panic!("Consistent Time: Attempted to measure between non-equal lens");
+    }
+    let mut count: usize = 0;
+    for i in 0..len {
+        count += popcount_u8(x[i] ^ y[i]) as usize;
+    }
+    count
+}
+#[test]
+fn test_ct_hamming_weight() {
+    assert_eq!( ct_hamming_weight(&[]), 0);
+    assert_eq!( ct_hamming_weight(&[0,0,0]), 0);
+    assert_eq!( ct_hamming_weight(&[0xFF,0xFF]), 16);
+    assert_eq!( ct_hamming_weight(&[0x01,0x80,0x55]), 6);
+}
+#[test]
+fn test_ct_hamming_distance() {
+    assert_eq!( ct_hamming_distance(&[0xFF,0x00], &[0xFF,0x00]), 0);
+    assert_eq!( ct_hamming_distance(&[0xFF,0x00], &[0x00,0xFF]), 16);
+    assert_eq!( ct_hamming_distance(&[0x0F,0x01], &[0x1F,0x03]), 2);
+}
+#[test]
+#[should_panic]
+fn test_ct_hamming_distance_panic() {
+    ct_hamming_distance(&[0,0], &[0]);
+}
+
+macro_rules! ct_all_zero_gen {
+    ($name:ident,$code:ident;;$test_name:ident) => {
+        ///Check if every element of a slice is zero.
+        ///
+        ///All elements are OR'd together and compared once at the end,
+        ///the position of a non-zero element is never revealed.
+        #[no_mangle]
+        pub extern "C" fn $name( x: &[$code]) -> bool {
+            let mut flag: $code = 0;
+            for i in 0..x.len() {
+                flag |= x[i];
+            }
+            <$code as ConstantTime>::ct_eq(flag,0)
+        }
+        #[test]
+        fn $test_name() {
+            const MAX: $code = max!($code);
+            let x: [$code;10] = [0,0,0,0,0,0,0,0,0,0];
+            let y: [$code;10] = [0,0,0,0,0,0,0,0,0,MAX];
+            let z: [$code;10] = [1,0,0,0,0,0,0,0,0,0];
+            assert_eq!( ct_all_zero( &x), true);
+            assert_eq!( ct_all_zero( &y), false);
+            assert_eq!( ct_all_zero( &z), false);
+            assert_eq!( ct_all_zero::<$code>( &[]), true);
+        }
+    }
+}
+ct_all_zero_gen!(ct_u8_all_zero,u8;;
+    test_ct_u8_all_zero);
+ct_all_zero_gen!(ct_u16_all_zero,u16;;
+    test_ct_u16_all_zero);
+ct_all_zero_gen!(ct_u32_all_zero,u32;;
+    test_ct_u32_all_zero);
+ct_all_zero_gen!(ct_u64_all_zero,u64;;
+    test_ct_u64_all_zero);
+ct_all_zero_gen!(ct_usize_all_zero,usize;;
+    test_ct_usize_all_zero);
+
+///Per element selection between two slices.
+///
+///IF flags[i] == True THEN dst[i] will be set to a[i]
+///
+///IF flags[i] == False THEN dst[i] will be set to b[i]
+///
+///This is `ct_copy` with one flag per element instead of one for the
+///whole slice. Every element is written reguardless of the flags.
+///
+///#Panic:
+///
+///This function will panic if the four slices are not equal length.
+pub fn ct_blend<T>(dst: &mut [T], a: &[T], b: &[T], flags: &[bool])
+  where T: ConstantTime + Copy {
+    let len = dst.len();
+    if len != a.len() || len != b.len() || len != flags.len() {
+        panic!("Consistent Time: Attempted to blend between non-equal lens");
+    }
+    for i in 0..len {
+        dst[i] = <T as ConstantTime>::ct_select(flags[i], a[i], b[i]);
+    }
+}
+#[test]
+fn test_ct_blend() {
+    let a: [u8;4] = [1,2,3,4];
+    let b: [u8;4] = [0xF1,0xF2,0xF3,0xF4];
+    let mut dst: [u8;4] = [0,0,0,0];
+    ct_blend(&mut dst, &a, &b, &[true,false,true,false]);
+    assert_eq!( dst, [1,0xF2,3,0xF4]);
+    ct_blend(&mut dst, &a, &b, &[true,true,true,true]);
+    assert_eq!( dst, a);
+    ct_blend(&mut dst, &a, &b, &[false,false,false,false]);
+    assert_eq!( dst, b);
+}
+#[test]
+#[should_panic]
+fn test_ct_blend_panic() {
+    let a: [u32;2] = [1,2];
+    let b: [u32;2] = [3,4];
+    let mut dst: [u32;2] = [0,0];
+    ct_blend(&mut dst, &a, &b, &[true]);
+}
+
+///Masked equality of byte slices.
+///
+///Only the bits set in `mask` are compared, `0xFF` enables a whole
+///byte and `0x00` marks it as don't-care. Every byte of all three
+///slices is read reguardless of the mask.
+///
+///Returns `false` if X and Y are not equal length, just like
+///`ct_eq_slice`.
+///
+///#Panic:
+///
+///This function will panic if `mask` is not as long as X.
+pub fn ct_eq_masked(x: &[u8], y: &[u8], mask: &[u8]) -> bool {
+    let len = x.len();
+    if len != mask.len() {
+        panic!("Consistent Time: Mask length does not match the input");
+    }
+    if len != y.len() {
+        return false;
+    }
+    let mut flag: u8 = 0;
+    for i in 0..len {
+        flag |= (x[i] ^ y[i]) & mask[i];
+    }
+    ct_u8_eq(flag, 0)
+}
+#[test]
+fn test_ct_eq_masked() {
+    let x: [u8;4] = [0x01,0xAA,0x02,0x0F];
+    let y: [u8;4] = [0x01,0x55,0x02,0xFF];
+    assert_eq!( ct_eq_masked(&x, &y, &[0xFF,0x00,0xFF,0x0F]), true);
+    assert_eq!( ct_eq_masked(&x, &y, &[0xFF,0x00,0xFF,0xFF]), false);
+    assert_eq!( ct_eq_masked(&x, &y, &[0xFF,0xFF,0xFF,0x0F]), false);
+    assert_eq!( ct_eq_masked(&x, &y, &[0,0,0,0]), true);
+    assert_eq!( ct_eq_masked(&x, &y[..3], &[0,0,0,0]), false);
+}
+#[test]
+#[should_panic]
+fn test_ct_eq_masked_panic() {
+    ct_eq_masked(&[1,2], &[1,2], &[0xFF]);
+}
+
+/*
+ * ASCII lowercase of a byte, 0x20 is OR'd in under a mask only if the
+ * byte is an uppercase letter.
+ */
+fn fold_ascii(c: u8) -> u8 {
+    let upper = !ct_u8_lt(c, b'A') & ct_u8_lt(c, b'Z' + 1);
+    c | ct_select_u8(upper, 0x20, 0)
+}
+///Check if two byte strings are equal ignoring ASCII case.
+///
+///Both sides are case folded arithmetically, then accumulated as in
+///`ct_u8_slice_eq`. Only the letters `A-Z` fold, every other byte has
+///to match exactly.
+///
+///Returns `false` right away if the lengths differ, this only depends
+///on the lengths.
+pub fn ct_eq_ignore_ascii_case(x: &[u8], y: &[u8]) -> bool {
+    if x.len() != y.len() {
+        return false;
+    }
+    let mut flag: u8 = 0;
+    for (x_val, y_val) in x.iter().zip(y.iter()) {
+        flag |= fold_ascii(*x_val) ^ fold_ascii(*y_val);
+    }
+    ct_u8_eq(flag, 0)
+}
+#[test]
+fn test_ct_eq_ignore_ascii_case() {
+    assert_eq!( ct_eq_ignore_ascii_case(b"Bearer abcDEF", b"bearer ABCdef"), true);
+    assert_eq!( ct_eq_ignore_ascii_case(b"abc", b"abd"), false);
+    assert_eq!( ct_eq_ignore_ascii_case(b"abc", b"ab"), false);
+    //'@' and '`', '[' and '{' differ by 0x20 but are not letters
+    assert_eq!( ct_eq_ignore_ascii_case(b"@[", b"`{"), false);
+    assert_eq!( ct_eq_ignore_ascii_case(b"\xC0", b"\xE0"), false);
+    assert_eq!( ct_eq_ignore_ascii_case(b"", b""), true);
+    for c in 0..256 {
+        assert_eq!( fold_ascii(c as u8), (c as u8).to_ascii_lowercase());
+    }
+}
+
+///Constant time compare and swap over buffers.
+///
+///IF buf == expected THEN buf will be set to new
+///
+///Otherwise buf is unchanged.
+///
+///Returns if the swap happened. The comparison result only ever
+///drives `ct_copy`, every element of `buf` is rewritten either way.
+///
+///#Panic:
+///
+///This function will panic if the three slices are not equal length.
+pub fn ct_cas<T>(buf: &mut [T], expected: &[T], new: &[T]) -> bool
+  where T: ConstantTime {
+    let len = buf.len();
+    if len != expected.len() || len != new.len() {
+        panic!("Consistent Time: Attempted to swap between non-equal lens");
+    }
+    let flag = <T as ConstantTime>::ct_eq_slice(buf, expected);
+    <T as ConstantTime>::ct_copy(flag, buf, new);
+    flag
+}
+#[test]
+fn test_ct_cas() {
+    let mut token: [u8;4] = [1,2,3,4];
+    assert_eq!( ct_cas(&mut token, &[1,2,3,5], &[0,0,0,0]), false);
+    assert_eq!( token, [1,2,3,4]);
+    assert_eq!( ct_cas(&mut token, &[1,2,3,4], &[0,0,0,0]), true);
+    assert_eq!( token, [0,0,0,0]);
+    assert_eq!( ct_cas(&mut token, &[1,2,3,4], &[9,9,9,9]), false);
+    assert_eq!( token, [0,0,0,0]);
+}
+#[test]
+#[should_panic]
+fn test_ct_cas_panic() {
+    let mut token: [u64;2] = [1,2];
+    ct_cas(&mut token, &[1,2], &[0]);
+}
+
+///Optional swapping of two values.
+///
+///IF flag == True THEN X and Y are exchanged
+///
+///If flag == False THEN both are unchanged
+///
+///Both values are rewritten reguardless of the flag.
+pub fn ct_swap<T>(flag: bool, x: &mut T, y: &mut T)
+  where T: ConstantTime + Copy {
+    let x_temp = *x;
+    let y_temp = *y;
+    *x = <T as ConstantTime>::ct_select(flag, y_temp, x_temp);
+    *y = <T as ConstantTime>::ct_select(flag, x_temp, y_temp);
+}
+#[test]
+fn test_ct_swap() {
+    let mut x: u16 = 1;
+    let mut y: u16 = max!(u16);
+    ct_swap(false, &mut x, &mut y);
+    assert_eq!( (x,y), (1,max!(u16)));
+    ct_swap(true, &mut x, &mut y);
+    assert_eq!( (x,y), (max!(u16),1));
+}
+
+///Optional swapping of two buffers.
+///
+///IF flag == True THEN X and Y are exchanged
+///
+///If flag == False THEN both are unchanged
+///
+///Every element of both buffers is rewritten reguardless of the flag.
+///
+///#Panic:
+///
+///This function will panic if the slices are not equal length.
+pub fn ct_swap_slice<T>(flag: bool, x: &mut [T], y: &mut [T])
+  where T: ConstantTime + Copy {
+    if x.len() != y.len() {
+        panic!("Consistent Time: Attempted to swap between non-equal lens");
+    }
+    for (x_val, y_val) in x.iter_mut().zip(y.iter_mut()) {
+        ct_swap(flag, x_val, y_val);
+    }
+}
+#[test]
+fn test_ct_swap_slice() {
+    let mut x: [u32;3] = [1,2,3];
+    let mut y: [u32;3] = [4,5,6];
+    ct_swap_slice(false, &mut x, &mut y);
+    assert_eq!( (x,y), ([1,2,3],[4,5,6]));
+    ct_swap_slice(true, &mut x, &mut y);
+    assert_eq!( (x,y), ([4,5,6],[1,2,3]));
+}
+#[test]
+#[should_panic]
+fn test_ct_swap_slice_panic() {
+    let mut x: [u32;3] = [1,2,3];
+    ct_swap_slice(true, &mut x, &mut [0;2]);
+}
+
+/*
+ * Optional reversal of x[start..end], built from the same fixed
+ * sequence of swaps no matter the flag.
+ */
+fn ct_reverse_if<T>(flag: bool, x: &mut [T], start: usize, end: usize)
+  where T: ConstantTime + Copy {
+    let mut lo = start;
+    let mut hi = end;
+    while lo + 1 < hi {
+        hi -= 1;
+        let (head, tail) = x.split_at_mut(hi);
+        ct_swap(flag, &mut head[lo], &mut tail[0]);
+        lo += 1;
+    }
+}
+
+///Rotate a slice left by a secret number of elements.
+///
+///This is a barrel rotation. For every bit of `offset` the slice is
+///optionally rotated by that power of two, each optional rotation
+///being three optional reversals. The sequence of memory accesses
+///only depends on the length of `x`, never on `offset`.
+///
+///`offset` may be larger than the slice, the rotation wraps around.
+pub fn ct_rotate_elements<T>(x: &mut [T], offset: usize)
+  where T: ConstantTime + Copy {
+    let len = x.len();
+    if len == 0 {
+        return;
+    }
+    let bits = ::core::mem::size_of::<usize>() * 8;
+    for k in 0..bits {
+        let amount = (1usize << k) % len;
+        let bit = ((offset >> k) & 1) as u8;
+        let flag = unsafe{trans::<u8,bool>(bit)};
+        ct_reverse_if(flag, x, 0, amount);
+        ct_reverse_if(flag, x, amount, len);
+        ct_reverse_if(flag, x, 0, len);
+    }
+}
+#[test]
+fn test_ct_rotate_elements() {
+    let base: [u8;7] = [0,1,2,3,4,5,6];
+    for offset in 0..20 {
+        let mut x = base;
+        let mut y = base;
+        ct_rotate_elements(&mut x, offset);
+        y.rotate_left(offset % 7);
+        assert_eq!( x, y);
+    }
+    let mut x = base;
+    ct_rotate_elements(&mut x, max!(usize));
+    let mut y = base;
+    y.rotate_left(max!(usize) % 7);
+    assert_eq!( x, y);
+    let mut z: [u32;0] = [];
+    ct_rotate_elements(&mut z, 3);
+}
+
+///Copy a region of secret position and length into a fixed buffer.
+///
+///`src[offset..offset + len]` is copied to the front of `dst` and the
+///rest of `dst` is zeroed. Every byte of `src` is read once and OR'd,
+///under a mask, into `dst` at its position modulo `dst.len()`, then
+///`dst` is rotated into place with `ct_rotate_elements`. The cost only
+///depends on the two buffer lengths.
+///
+///Returns the masked length, `len` if the region is inside `src` and
+///fits in `dst`, else `0` with `dst` all zero.
+pub fn ct_extract(src: &[u8], offset: usize, len: usize, dst: &mut [u8]) -> usize {
+    let cap = dst.len();
+    for d in dst.iter_mut() {
+        *d = 0;
+    }
+    if cap == 0 {
+        return 0;
+    }
+    let rest = ct_select_usize(ct_usize_lt(src.len(), offset), 0, src.len().wrapping_sub(offset));
+    let fits = !ct_usize_lt(cap, len) & !ct_usize_lt(rest, len);
+    let len = ct_select_usize(fits, len, 0);
+    let end = offset.wrapping_add(len);
+    let mut j: usize = 0;
+    let mut rot: usize = 0;
+    for (i, &b) in src.iter().enumerate() {
+        rot = ct_select_usize(ct_usize_eq(i, offset), j, rot);
+        let inside = !ct_usize_lt(i, offset) & ct_usize_lt(i, end);
+        dst[j] |= ct_select_u8(inside, b, 0);
+        j = if j + 1 == cap { 0 } else { j + 1 };
+    }
+    ct_rotate_elements(dst, rot);
+    len
+}
+#[test]
+fn test_ct_extract() {
+    let mut src = [0u8;40];
+    for i in 0..40 {
+        src[i] = i as u8 + 1;
+    }
+    for offset in 0..41 {
+        for len in 0..12 {
+            let mut dst = [0xFFu8;11];
+            let got = ct_extract(&src, offset, len, &mut dst);
+            if offset + len <= 40 && len <= 11 {
+                assert_eq!( got, len);
+                assert_eq!( &dst[..len], &src[offset..(offset + len)]);
+                assert_eq!( &dst[len..], &[0u8;11][len..]);
+            } else {
+                assert_eq!( got, 0);
+                assert_eq!( dst, [0u8;11]);
+            }
+        }
+    }
+    let mut dst = [0xFFu8;4];
+    assert_eq!( ct_extract(&src, max!(usize), 2, &mut dst), 0);
+    assert_eq!( ct_extract(&src, 2, max!(usize), &mut dst), 0);
+    assert_eq!( ct_extract(&src, 0, 0, &mut []), 0);
+}
+
+///Equality of slices of slices.
+///
+///Every inner pair is compared with `ct_eq_slice` and the results are
+///folded together, a mismatch in the first part does not stop the
+///comparison of the remaining parts.
+///
+///Returns `false` if the outer lengths, or any pair of inner lengths,
+///differ.
+pub fn ct_eq_nested<T>(x: &[&[T]], y: &[&[T]]) -> bool
+  where T: ConstantTime {
+    let len = x.len();
+    if len != y.len() {
+        return false;
+    }
+    let mut flag = true;
+    for i in 0..len {
+        flag &= <T as ConstantTime>::ct_eq_slice(x[i], y[i]);
+    }
+    flag
+}
+#[test]
+fn test_ct_eq_nested() {
+    let a: [&[u8];3] = [b"user", b"", b"mac-tag"];
+    let b: [&[u8];3] = [b"user", b"", b"mac-tag"];
+    let c: [&[u8];3] = [b"usEr", b"", b"mac-tag"];
+    let d: [&[u8];3] = [b"user", b"", b"mac-ta"];
+    assert_eq!( ct_eq_nested(&a, &b), true);
+    assert_eq!( ct_eq_nested(&a, &c), false);
+    assert_eq!( ct_eq_nested(&a, &d), false);
+    assert_eq!( ct_eq_nested(&a, &b[..2]), false);
+    assert_eq!( ct_eq_nested::<u8>(&[], &[]), true);
+}
+
+/*
+ * Little endian load of 8 bytes. Offsets are constants in the
+ * unrolled callers below, so this is a plain (unaligned) load.
+ */
+#[inline(always)]
+fn load_word(x: &[u8], off: usize) -> u64 {
+    (x[off] as u64) |
+    ((x[off + 1] as u64) << 8) |
+    ((x[off + 2] as u64) << 16) |
+    ((x[off + 3] as u64) << 24) |
+    ((x[off + 4] as u64) << 32) |
+    ((x[off + 5] as u64) << 40) |
+    ((x[off + 6] as u64) << 48) |
+    ((x[off + 7] as u64) << 56)
+}
+
+macro_rules! ct_eq_fixed_gen {
+    ($name:ident,$len:expr,$($off:expr),*;;$test_name:ident) => {
+        ///Equality of fixed size byte arrays.
+        ///
+        ///Fully unrolled, the arrays are XOR'd a 64bit word at a time
+        ///into a single accumulator which is compared once at the end.
+        ///There is no length check and no loop.
+        #[no_mangle]
+        #[inline(never)]
+        pub extern "C" fn $name( x: &[u8;$len], y: &[u8;$len]) -> bool {
+            let mut flag: u64 = 0;
+            $(
+                flag |= load_word(x,$off) ^ load_word(y,$off);
+            )*
+            ct_u64_eq(flag,0)
+        }
+        #[test]
+        fn $test_name() {
+            let x: [u8;$len] = [0xA5;$len];
+            assert_eq!( $name(&x, &x), true);
+            assert_eq!( $name(&[0;$len], &[0;$len]), true);
+            assert_eq!( $name(&x, &[0;$len]), false);
+            for i in 0..$len {
+                for bit in 0..8 {
+                    let mut y = x;
+                    y[i] ^= 1 << bit;
+                    assert_eq!( $name(&x, &y), false);
+                }
+            }
+        }
+    }
+}
+ct_eq_fixed_gen!(ct_eq_16,16,0,8;;
+    test_ct_eq_16);
+ct_eq_fixed_gen!(ct_eq_32,32,0,8,16,24;;
+    test_ct_eq_32);
+ct_eq_fixed_gen!(ct_eq_64,64,0,8,16,24,32,40,48,56;;
+    test_ct_eq_64);
+
+///A failed verification.
+///
+///Carries nothing about why it failed, every failure builds the same
+///value.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub struct CtError;
+impl ::core::fmt::Display for CtError {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        f.write_str("verification failed")
+    }
+}
+
+macro_rules! ct_verify_tag_gen {
+    ($name:ident,$check:ident,$eq:ident,$len:expr;;$test_name:ident) => {
+        ///Verify a MAC tag of a fixed size.
+        ///
+        ///The same unrolled comparison as the matching `ct_eq_`
+        ///function, the sizes are in the types so there is nothing to
+        ///get wrong about lengths.
+        #[must_use]
+        #[inline(always)]
+        pub fn $name( expected: &[u8;$len], computed: &[u8;$len]) -> bool {
+            $eq(expected, computed)
+        }
+        ///Verify a MAC tag of a fixed size, for use with `?`.
+        ///
+        ///The comparison is done in full before the result is looked
+        ///at, only the final answer is branched on.
+        #[inline(always)]
+        pub fn $check( expected: &[u8;$len], computed: &[u8;$len]) -> Result<(), CtError> {
+            if $name(expected, computed) {
+                Ok(())
+            } else {
+                Err(CtError)
+            }
+        }
+        #[test]
+        fn $test_name() {
+            let tag = [0x5Cu8;$len];
+            let mut other = tag;
+            other[$len - 1] ^= 0x80;
+            assert_eq!( $name(&tag, &tag), true);
+            assert_eq!( $name(&tag, &other), false);
+            assert_eq!( $check(&tag, &tag), Ok(()));
+            assert_eq!( $check(&tag, &other), Err(CtError));
+        }
+    }
+}
+ct_verify_tag_gen!(ct_verify_tag_16,ct_check_tag_16,ct_eq_16,16;;
+    test_ct_verify_tag_16);
+ct_verify_tag_gen!(ct_verify_tag_32,ct_check_tag_32,ct_eq_32,32;;
+    test_ct_verify_tag_32);
+ct_verify_tag_gen!(ct_verify_tag_64,ct_check_tag_64,ct_eq_64,64;;
+    test_ct_verify_tag_64);
+
+///Double HMAC comparison, both inputs are passed through a keyed PRF
+///and the outputs are compared.
+///
+///`prf(input, out)` must write a keyed MAC of `input` (HMAC with a
+///fresh random key is the usual choice) into `out`, a 64 byte buffer
+///that starts zeroed. Anything the PRF leaves untouched stays zero for
+///both inputs. An attacker who does not know the key can not steer
+///the compared bytes, so a timing leak in the comparison or in how the
+///lengths differ does not reveal anything about `a` or `b`. The cost
+///of the PRF itself still depends on the input lengths.
+pub fn ct_eq_blinded<F>(a: &[u8], b: &[u8], mut prf: F) -> bool
+  where F: FnMut(&[u8], &mut [u8]) {
+    let mut tag_a = [0u8;64];
+    let mut tag_b = [0u8;64];
+    prf(a, &mut tag_a);
+    prf(b, &mut tag_b);
+    ct_eq_64(&tag_a, &tag_b)
+}
+#[test]
+fn test_ct_eq_blinded() {
+    //stand in for a keyed hash
+    let prf = |input: &[u8], out: &mut [u8]| {
+        let mut acc: u64 = 0x9E3779B97F4A7C15 ^ input.len() as u64;
+        for &x in input.iter() {
+            acc = (acc ^ x as u64).wrapping_mul(0x100000001B3);
+        }
+        for (i, o) in out[..32].iter_mut().enumerate() {
+            *o = (acc >> ((i % 8) * 8)) as u8 ^ i as u8;
+        }
+    };
+    assert_eq!( ct_eq_blinded(b"secret token", b"secret token", prf), true);
+    assert_eq!( ct_eq_blinded(b"secret token", b"secret tokeN", prf), false);
+    assert_eq!( ct_eq_blinded(b"secret token", b"secret", prf), false);
+    assert_eq!( ct_eq_blinded(b"", b"", prf), true);
+}
+
+///Constant time comparison for use with `?`.
+///
+///`ct_eq_slice` with the result turned into a `Result`, the comparison
+///is done in full first. Every failure, including differing lengths,
+///gives the same `CtError`.
+#[must_use = "the comparison is pointless unless the error is handled"]
+pub fn ct_verify<T>(a: &[T], b: &[T]) -> Result<(), CtError>
+  where T: ConstantTime {
+    if <T as ConstantTime>::ct_eq_slice(a, b) {
+        Ok(())
+    } else {
+        Err(CtError)
+    }
+}
+#[test]
+fn test_ct_verify() {
+    assert_eq!( ct_verify(b"token", b"token"), Ok(()));
+    assert_eq!( ct_verify(b"token", b"tokem"), Err(CtError));
+    assert_eq!( ct_verify(b"token", b"toke"), Err(CtError));
+    assert_eq!( ct_verify::<u32>(&[1, 2], &[1, 2]), Ok(()));
+    let check = |x: &[u8]| -> Result<u8, CtError> {
+        ct_verify(x, b"ok")?;
+        Ok(1)
+    };
+    assert_eq!( check(b"ok"), Ok(1));
+    assert_eq!( check(b"no"), Err(CtError));
+}
+
+///Compare two slices in constant time and return early with an error
+///if they differ.
+///
+///`ensure_ct_eq!(a, b, err)` returns `Err(err)` from the enclosing
+///function, `ensure_ct_eq!(a, b)` returns `Err(CtError)`. The whole
+///comparison runs first with `ct_eq_slice`, only its final answer is
+///branched on.
+#[macro_export]
+macro_rules! ensure_ct_eq {
+    ($a:expr, $b:expr) => {
+        if !$crate::ct_eq_slice(&$a[..], &$b[..]) {
+            return Err($crate::CtError);
+        }
+    };
+    ($a:expr, $b:expr, $err:expr) => {
+        if !$crate::ct_eq_slice(&$a[..], &$b[..]) {
+            return Err($err);
+        }
+    };
+}
+#[test]
+fn test_ensure_ct_eq() {
+    fn check(x: &[u8]) -> Result<u8, CtError> {
+        ensure_ct_eq!(x, b"ok");
+        Ok(1)
+    }
+    fn check_with(x: [u16;2]) -> Result<u8, &'static str> {
+        ensure_ct_eq!(x, [7u16, 9], "bad tag");
+        Ok(2)
+    }
+    assert_eq!( check(b"ok"), Ok(1));
+    assert_eq!( check(b"no"), Err(CtError));
+    assert_eq!( check(b"okay"), Err(CtError));
+    assert_eq!( check_with([7, 9]), Ok(2));
+    assert_eq!( check_with([7, 8]), Err("bad tag"));
+}
+
+///Branchless `if`, `ct_if!(flag, { a }, { b })`.
+///
+///Both blocks are always evaluated, in order, and the result is picked
+///with `ct_select`. The blocks must have the same type and it must
+///implement `ConstantTime`, anything else is a compile error rather
+///than a silent branch. Side effects in either block always happen.
+#[macro_export]
+macro_rules! ct_if {
+    ($flag:expr, $then:block, $else:block) => {{
+        let then_val = $then;
+        let else_val = $else;
+        $crate::ct_select($flag, then_val, else_val)
+    }};
+}
+#[test]
+fn test_ct_if() {
+    let secret: u32 = 0xDEAD;
+    let x = ct_if!(ct_u32_eq(secret, 0xDEAD), { 1u64 << 40 }, { 7u64 });
+    assert_eq!( x, 1 << 40);
+    let y: u8 = ct_if!(ct_u32_lt(secret, 5), { 1 }, { 2 });
+    assert_eq!( y, 2);
+    let mut calls = 0;
+    let z = ct_if!(true, { calls += 1; 3usize }, { calls += 1; 4usize });
+    assert_eq!( (z, calls), (3, 2));
+}
+
+///Pick the status or alert code to send, `ok_code` if `flag` is true
+///else `err_code`.
+///
+///`ct_select` under a name that says what it is for, so the code that
+///decides on a response does not end up in an `if`.
+#[inline]
+pub fn ct_select_error<T>(flag: bool, ok_code: T, err_code: T) -> T
+  where T: ConstantTime {
+    <T as ConstantTime>::ct_select(flag, ok_code, err_code)
+}
+///Copy the response template at a secret `index` into `out`.
+///
+///Every template is copied under a mask, the one at `index` is kept.
+///All templates must be the length of `out`, pad them to a common
+///length if they are not. An out of range `index` leaves `out` as it
+///was.
+///
+///#Panic:
+///
+///This function will panic if any template is not the length of `out`.
+pub fn ct_select_response<T>(out: &mut [T], templates: &[&[T]], index: usize)
+  where T: ConstantTime {
+    for t in templates.iter() {
+        if t.len() != out.len() {
+            panic!("Consistent Time: Attempted to select between non-equal lens");
+        }
+    }
+    for (i, t) in templates.iter().enumerate() {
+        <T as ConstantTime>::ct_copy(ct_usize_eq(i, index), out, t);
+    }
+}
+#[test]
+fn test_ct_select_error() {
+    assert_eq!( ct_select_error(true, 0u16, 20), 0);
+    assert_eq!( ct_select_error(false, 0u16, 20), 20);
+    let templates: [&[u8];3] = [b"200 OK   ", b"401 AUTH ", b"403 DENY "];
+    for i in 0..3 {
+        let mut out = [0u8;9];
+        ct_select_response(&mut out, &templates, i);
+        assert_eq!( &out, templates[i]);
+    }
+    let mut out = [0u8;9];
+    ct_select_response(&mut out, &templates, 3);
+    assert_eq!( out, [0u8;9]);
+}
+#[test]
+#[should_panic]
+fn test_ct_select_response_panic() {
+    let templates: [&[u8];2] = [b"OK", b"DENIED"];
+    let mut out = [0u8;2];
+    ct_select_response(&mut out, &templates, 0);
+}
+#[test]
+fn test_ct_select_response_panic_untouched() {
+    extern crate std;
+    //the bad template comes after the selected one, out must not have
+    //been written when the panic happens
+    let templates: [&[u8];3] = [b"OK", b"NO", b"DENIED"];
+    let mut out = [0u8;2];
+    let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        ct_select_response(&mut out, &templates, 0);
+    }));
+    assert!( res.is_err());
+    assert_eq!( out, [0u8;2]);
+}
+
+///Compare a presented credential against a stored one that may not
+///exist.
+///
+///Two comparisons always run, `presented` against `stored` (or `dummy`
+///when there is no account) and `presented` against `dummy`, and the
+///one that counts is picked with `ct_select`. Nothing branches on
+///whether the account exists, so a login for an unknown account costs
+///the same as a wrong password for a known one. `dummy` must be the
+///same length as the real stored values, e.g. the hash of a random
+///password, or the lengths will differ.
+///
+///Returns `true` only if the account exists and the values match.
+pub fn ct_verify_or_dummy(stored: Option<&[u8]>, presented: &[u8], dummy: &[u8]) -> bool {
+    verify_or_dummy_with(stored, presented, dummy, <u8 as ConstantTime>::ct_eq_slice)
+}
+/*
+ * Body of ct_verify_or_dummy with the comparison passed in, so the
+ * test can count the comparisons made.
+ */
+fn verify_or_dummy_with<F>(stored: Option<&[u8]>, presented: &[u8], dummy: &[u8], mut eq: F) -> bool
+  where F: FnMut(&[u8], &[u8]) -> bool {
+    let exists = stored.is_some();
+    let real = eq(stored.unwrap_or(dummy), presented);
+    let fake = eq(dummy, presented);
+    let hit = unsafe{trans::<u8,bool>(ct_select_u8(exists, real as u8, fake as u8))};
+    hit & exists
+}
+#[test]
+fn test_ct_verify_or_dummy() {
+    let dummy = [0x5Au8;32];
+    let stored = [0x11u8;32];
+    assert_eq!( ct_verify_or_dummy(Some(&stored), &stored, &dummy), true);
+    assert_eq!( ct_verify_or_dummy(Some(&stored), &dummy, &dummy), false);
+    //matching the dummy is no way in
+    assert_eq!( ct_verify_or_dummy(None, &dummy, &dummy), false);
+    assert_eq!( ct_verify_or_dummy(None, &stored, &dummy), false);
+    //both paths compare the same number of bytes
+    let cases: [(Option<&[u8]>, &[u8]);4] = [
+        (Some(&stored), &stored),
+        (Some(&stored), &dummy),
+        (None, &stored),
+        (None, &dummy),
+    ];
+    for &(s, p) in cases.iter() {
+        let mut calls = 0;
+        let mut bytes = 0;
+        verify_or_dummy_with(s, p, &dummy, |x, y| {
+            calls += 1;
+            bytes += x.len() + y.len();
+            ct_eq_slice(x, y)
+        });
+        assert_eq!( (calls, bytes), (2, 128));
+    }
+}
+
+///Constant time set membership.
+///
+///`value` is compared against every entry of `set` and the results are
+///OR'd together. Checking a secret identifier against an allow or
+///revocation list does not reveal which entry matched.
+pub fn ct_set_contains<T>(set: &[T], value: T) -> bool
+  where T: ConstantTime + Copy {
+    let mut found = false;
+    for i in 0..set.len() {
+        found |= <T as ConstantTime>::ct_eq(set[i], value);
+    }
+    found
+}
+#[test]
+fn test_ct_set_contains() {
+    let revoked: [u64;4] = [1001,1002,2048,max!(u64)];
+    assert_eq!( ct_set_contains(&revoked, 1001), true);
+    assert_eq!( ct_set_contains(&revoked, 2048), true);
+    assert_eq!( ct_set_contains(&revoked, max!(u64)), true);
+    assert_eq!( ct_set_contains(&revoked, 1003), false);
+    assert_eq!( ct_set_contains::<u64>(&[], 0), false);
+}
+
+///XOR a byte slice into another.
+///
+///X will be set to X ^ Y. There is nothing data dependent about XOR,
+///this exists so keystream and mask application has a home next to
+///the other slice operations (and a vectorized twin in `simd`). The
+///`wasm-simd` feature vectorizes it on wasm32.
+///
+///#Panic:
+///
+///This function will panic if X and Y are not equal length.
+pub fn ct_xor_slice(x: &mut [u8], y: &[u8]) {
+    let len = x.len();
+    if len != y.len() {
+        panic!("Consistent Time: Attempted to xor between non-equal lens");
+    }
+    let done = arch::xor_u8(x, y);
+    for (x_val, y_val) in x[done..].iter_mut().zip(y[done..].iter()) {
+        *x_val ^= y_val;
+    }
+}
+#[test]
+fn test_ct_xor_slice() {
+    let mut x: [u8;4] = [0x00,0xFF,0x0F,0xAA];
+    ct_xor_slice(&mut x, &[0xFF,0xFF,0xF0,0xAA]);
+    assert_eq!( x, [0xFF,0x00,0xFF,0x00]);
+}
+#[test]
+#[should_panic]
+fn test_ct_xor_slice_panic() {
+    let mut x: [u8;4] = [0;4];
+    ct_xor_slice(&mut x, &[0;3]);
+}
+
+///Compare up to 64 pairs of byte slices at once.
+///
+///Bit `i` of the result is set if `pairs[i].0 == pairs[i].1`. Every
+///pair is fully compared and every result bit is written, so looping
+///over the result afterwards can not reveal which pair failed first
+///from how long the comparison took.
+///
+///#Panic:
+///
+///This function will panic if more than 64 pairs are given.
+pub fn ct_eq_batch(pairs: &[(&[u8], &[u8])]) -> u64 {
+    if pairs.len() > 64 {
+        panic!("Consistent Time: At most 64 pairs fit in a batch");
+    }
+    let mut bits: u64 = 0;
+    for (i, pair) in pairs.iter().enumerate() {
+        let hit: u8 = unsafe{trans::<bool,u8>(ct_u8_slice_eq(pair.0, pair.1))};
+        bits |= (hit as u64) << i;
+    }
+    bits
+}
+#[test]
+fn test_ct_eq_batch() {
+    let good: &[u8] = b"tag-good";
+    let bad: &[u8] = b"tag-badd";
+    assert_eq!( ct_eq_batch(&[]), 0);
+    assert_eq!( ct_eq_batch(&[(good, good), (good, bad), (bad, bad)]), 0b101);
+    assert_eq!( ct_eq_batch(&[(good, &good[..4])]), 0);
+    let mut pairs: [(&[u8], &[u8]);64] = [(good, good);64];
+    assert_eq!( ct_eq_batch(&pairs), max!(u64));
+    pairs[63] = (good, bad);
+    assert_eq!( ct_eq_batch(&pairs), max!(u64) >> 1);
+}
+#[test]
+#[should_panic]
+fn test_ct_eq_batch_panic() {
+    let x: &[u8] = b"x";
+    let pairs: [(&[u8], &[u8]);65] = [(x, x);65];
+    ct_eq_batch(&pairs);
+}
+
+///Streaming equality against an expected value.
+///
+///Input is fed in chunks of any size with `update` and checked with
+///`finalize`. Differences are OR'd into a single accumulator, nothing
+///about the content is decided before `finalize`. The total length
+///of the input is public, a length mismatch is only reported at the
+///end.
+#[derive(Clone)]
+pub struct CtEqStream<'a> {
+    expected: &'a [u8],
+    pos: usize,
+    flag: u8,
+    overflow: bool,
+}
+impl<'a> CtEqStream<'a> {
+    ///Start a comparison against `expected`.
+    pub fn new(expected: &'a [u8]) -> CtEqStream<'a> {
+        CtEqStream{ expected, pos: 0, flag: 0, overflow: false }
+    }
+    ///Feed the next chunk of input.
+    pub fn update(&mut self, chunk: &[u8]) {
+        let remaining = self.expected.len() - self.pos;
+        let len = if chunk.len() > remaining {
+            self.overflow = true;
+            remaining
+        } else {
+            chunk.len()
+        };
+        let expected = &self.expected[self.pos..(self.pos + len)];
+        for (x_val, y_val) in expected.iter().zip(chunk[..len].iter()) {
+            self.flag |= x_val ^ y_val;
+        }
+        self.pos += len;
+    }
+    ///Finish the comparison.
+    ///
+    ///Returns `true` only if the input had exactly the expected length
+    ///and content.
+    pub fn finalize(self) -> bool {
+        let complete = !self.overflow & ct_usize_eq(self.pos, self.expected.len());
+        ct_u8_eq(self.flag, 0) & complete
+    }
+}
+#[test]
+fn test_ct_eq_stream() {
+    let expected = b"0123456789abcdef";
+    let mut s = CtEqStream::new(expected);
+    for chunk in expected.chunks(3) {
+        s.update(chunk);
+    }
+    assert_eq!( s.finalize(), true);
+    let mut s = CtEqStream::new(expected);
+    s.update(b"0123456789");
+    s.update(b"");
+    s.update(b"abcdeF");
+    assert_eq!( s.finalize(), false);
+    let mut s = CtEqStream::new(expected);
+    s.update(b"0123456789");
+    assert_eq!( s.finalize(), false);
+    let mut s = CtEqStream::new(expected);
+    s.update(expected);
+    s.update(b"x");
+    assert_eq!( s.finalize(), false);
+    assert_eq!( CtEqStream::new(b"").finalize(), true);
+}
+
+///Commit a shadow buffer over a live buffer.
+///
+///The "compute both, commit one" pattern: the candidate result is
+///always built in `shadow`, unconditionally, then this decides in one
+///pass whether it replaces `live`. This is how an FO transform
+///decapsulation swaps in the implicit rejection key.
+///
+///IF flag == True THEN live will be set to shadow
+///
+///If flag == False THEN live is unchanged
+///
+///The copy is `ct_copy`, this exists so the lengths are checked here,
+///in Rust. `ct_copy` panics inside of an `extern "C"` function and a
+///panic can not unwind out of one, the process aborts instead.
+///
+///#Panic:
+///
+///This function will panic if live and shadow are not equal length.
+pub fn ct_commit<T>(flag: bool, live: &mut [T], shadow: &[T])
+  where T: ConstantTime {
+    if live.len() != shadow.len() {
+        panic!("Consistent Time: Attempted to commit between non-equal lens");
+    }
+    <T as ConstantTime>::ct_copy(flag, live, shadow);
+}
+#[test]
+fn test_ct_commit() {
+    let mut live: [u8;4] = [1,1,1,1];
+    let mut shadow: [u8;4] = [0;4];
+    for i in 0..4 {
+        shadow[i] = live[i] ^ 0xFF;
+    }
+    ct_commit(false, &mut live, &shadow);
+    assert_eq!( live, [1,1,1,1]);
+    ct_commit(true, &mut live, &shadow);
+    assert_eq!( live, [0xFE,0xFE,0xFE,0xFE]);
+}
+#[test]
+#[should_panic]
+fn test_ct_commit_panic() {
+    let mut live: [u32;4] = [0;4];
+    ct_commit(true, &mut live, &[0;2]);
+}
+
+///Accumulator for constant iteration rejection sampling.
+///
+///Every candidate is offered, accepted or not. The first accepted one
+///is committed with `ct_select` and a `done` flag is kept as a mask so
+///that later candidates are masked out instead of skipped. The caller
+///runs a fixed number of iterations and checks `finish` at the end,
+///the number of rejections never shows up in the timing.
+#[derive(Clone,Copy)]
+pub struct CtRejectionSampler<T> {
+    value: T,
+    done: bool,
+}
+impl<T> CtRejectionSampler<T>
+  where T: ConstantTime + Copy {
+    ///Start with nothing accepted, `fallback` is what `finish` returns
+    ///if that never changes.
+    pub fn new(fallback: T) -> CtRejectionSampler<T> {
+        CtRejectionSampler{ value: fallback, done: false }
+    }
+    ///Offer a candidate, it is committed if `accept` is true and no
+    ///candidate was committed before.
+    pub fn offer(&mut self, candidate: T, accept: bool) {
+        let take = accept & !self.done;
+        self.value = <T as ConstantTime>::ct_select(take, candidate, self.value);
+        self.done |= accept;
+    }
+    ///If a candidate has been committed.
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+    ///Returns if a candidate was committed and the committed value, or
+    ///the fallback.
+    pub fn finish(self) -> (bool, T) {
+        (self.done, self.value)
+    }
+}
+///Constant iteration rejection sampling.
+///
+///`sample` is called exactly `iterations` times, each call returns a
+///candidate and if it is acceptable. The first acceptable candidate is
+///returned with `true`, if there is none `fallback` is returned with
+///`false`. `sample` must itself compute the candidate and the
+///predicate without branching on them.
+pub fn ct_rejection_sample<T, F>(iterations: usize, fallback: T, mut sample: F) -> (bool, T)
+  where T: ConstantTime + Copy,
+        F: FnMut() -> (T, bool) {
+    let mut sampler = CtRejectionSampler::new(fallback);
+    for _ in 0..iterations {
+        let (candidate, accept) = sample();
+        sampler.offer(candidate, accept);
+    }
+    sampler.finish()
+}
+#[test]
+fn test_ct_rejection_sample() {
+    let mut s = CtRejectionSampler::new(0u32);
+    s.offer(7, false);
+    assert_eq!( s.is_done(), false);
+    s.offer(9, true);
+    s.offer(11, true);
+    assert_eq!( s.finish(), (true, 9));
+
+    //uniform below 10 from a counter standing in for random bytes
+    let mut counter: u8 = 251;
+    let mut calls = 0;
+    let res = ct_rejection_sample(8, 0u8, || {
+        calls += 1;
+        counter = counter.wrapping_add(3);
+        (counter % 10, ct_u8_lt(counter, 250))
+    });
+    assert_eq!( res, (true, 1));
+    assert_eq!( calls, 8);
+    assert_eq!( ct_rejection_sample(4, 42u64, || (1, false)), (false, 42));
+}
+
+///C compatible constant time `memcmp`.
+///
+///Returns a negative value, zero, or a positive value if the first
+///`len` bytes at X are less than, equal to, or greater than those at
+///Y. Unlike `memcmp` all `len` bytes are always read, the sign is
+///computed with masks by `ct_u8_slice_cmp`. The result is always
+///exactly -1, 0 or 1.
+///
+/// # Safety
+///
+///X and Y must each be valid for reads of `len` bytes. They may be
+///null if `len` is zero.
+#[no_mangle]
+pub unsafe extern "C" fn ct_memcmp(x: *const u8, y: *const u8, len: usize) -> i32 {
+    if len == 0 {
+        return 0;
+    }
+    let x = ::core::slice::from_raw_parts(x, len);
+    let y = ::core::slice::from_raw_parts(y, len);
+    ct_u8_slice_cmp(x, y) as i32
+}
+#[test]
+fn test_ct_memcmp() {
+    let x = b"\x00\x01\x02\x03";
+    let y = b"\x00\x01\x03\x00";
+    unsafe {
+        assert_eq!( ct_memcmp(x.as_ptr(), x.as_ptr(), 4), 0);
+        assert_eq!( ct_memcmp(x.as_ptr(), y.as_ptr(), 4), -1);
+        assert_eq!( ct_memcmp(y.as_ptr(), x.as_ptr(), 4), 1);
+        assert_eq!( ct_memcmp(x.as_ptr(), y.as_ptr(), 2), 0);
+        assert_eq!( ct_memcmp(::core::ptr::null(), ::core::ptr::null(), 0), 0);
+    }
+}
@@ -0,0 +1,759 @@
+//Copyright 2016 William Cody Laeder
+//
+//Licensed under the Apache License, Version 2.0 (the "License");
+//you may not use this file except in compliance with the License.
+//you may obtain a copy of the License at
+//
+//  http://www.apache.org/licenses/LICENSE-2.0
+//
+//Unless required by applicable law or agreed to in writing, software
+//distrubuted under the License is distrubuted on as "AS IS" BASIS,
+//WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//See the License for the specific language governing permissions and
+//limitations under the License.
+
+
+//!Little endian limb slices
+//!
+//!Building blocks for constant time multi precision arithmetic. A
+//!number is a `&[u64]` with the least significant limb first. Loops
+//!only ever depend on the (public) number of limbs.
+
+use super::{ct_select_u64};
+use super::arith::{ct_adc_u64, ct_sbb_u64};
+
+/*
+ * Bits of a shift are consumed one at a time, for bit k the slice is
+ * shifted by 2^k with every limb rewritten through ct_select_u64.
+ * Shifting by the full width or more simply clears the slice, so the
+ * secret may be any usize.
+ */
+fn bit_flag(x: usize, k: usize) -> bool {
+    let bit = ((x >> k) & 1) as u8;
+    unsafe{::core::mem::transmute::<u8,bool>(bit)}
+}
+
+///Shift a limb slice left by a secret number of bits.
+///
+///Bits shifted past the most significant limb are lost. Every limb is
+///rewritten for every bit of `shift`, the amount never changes the
+///memory access pattern.
+pub fn ct_shl_slice(x: &mut [u64], shift: usize) {
+    let len = x.len();
+    let bits = ::core::mem::size_of::<usize>() * 8;
+    for k in 0..bits {
+        let flag = bit_flag(shift, k);
+        let amount = 1u128 << k;
+        let limbs = (amount / 64) as usize;
+        let b = (amount % 64) as u32;
+        for i in (0..len).rev() {
+            let hi = if i >= limbs { x[i - limbs] } else { 0 };
+            let lo = if i > limbs { x[i - limbs - 1] } else { 0 };
+            let val = if b == 0 { hi } else { (hi << b) | (lo >> (64 - b)) };
+            x[i] = ct_select_u64(flag, val, x[i]);
+        }
+    }
+}
+///Shift a limb slice right by a secret number of bits.
+///
+///Bits shifted past the least significant limb are lost. Every limb is
+///rewritten for every bit of `shift`, the amount never changes the
+///memory access pattern.
+pub fn ct_shr_slice(x: &mut [u64], shift: usize) {
+    let len = x.len();
+    let bits = ::core::mem::size_of::<usize>() * 8;
+    for k in 0..bits {
+        let flag = bit_flag(shift, k);
+        let amount = 1u128 << k;
+        let limbs = (amount / 64) as usize;
+        let b = (amount % 64) as u32;
+        for i in 0..len {
+            let lo = if i + limbs < len { x[i + limbs] } else { 0 };
+            let hi = if i + limbs + 1 < len { x[i + limbs + 1] } else { 0 };
+            let val = if b == 0 { lo } else { (lo >> b) | (hi << (64 - b)) };
+            x[i] = ct_select_u64(flag, val, x[i]);
+        }
+    }
+}
+#[test]
+fn test_ct_shl_slice() {
+    let base: u128 = 0x0123_4567_89AB_CDEF_FEDC_BA98_7654_3210;
+    for shift in 0..140 {
+        let mut x: [u64;2] = [base as u64, (base >> 64) as u64];
+        ct_shl_slice(&mut x, shift);
+        let want = if shift < 128 { base << shift } else { 0 };
+        assert_eq!( x, [want as u64, (want >> 64) as u64]);
+    }
+    let mut x: [u64;3] = [1,2,3];
+    ct_shl_slice(&mut x, usize::MAX);
+    assert_eq!( x, [0,0,0]);
+}
+#[test]
+fn test_ct_shr_slice() {
+    let base: u128 = 0x0123_4567_89AB_CDEF_FEDC_BA98_7654_3210;
+    for shift in 0..140 {
+        let mut x: [u64;2] = [base as u64, (base >> 64) as u64];
+        ct_shr_slice(&mut x, shift);
+        let want = if shift < 128 { base >> shift } else { 0 };
+        assert_eq!( x, [want as u64, (want >> 64) as u64]);
+    }
+    let mut x: [u64;3] = [1,2,3];
+    ct_shr_slice(&mut x, usize::MAX);
+    assert_eq!( x, [0,0,0]);
+}
+
+/*
+ * Carry chains over equal length limb slices. `mask` is all ones or
+ * zero and is applied to every limb of `y`, so a masked add or sub
+ * costs the same as a real one.
+ */
+pub(crate) fn add_masked(x: &mut [u64], y: &[u64], mask: u64) -> u64 {
+    let mut carry: u64 = 0;
+    for (x_val, y_val) in x.iter_mut().zip(y.iter()) {
+        let (sum, c) = ct_adc_u64(*x_val, y_val & mask, carry);
+        *x_val = sum;
+        carry = c;
+    }
+    carry
+}
+pub(crate) fn sub_masked(x: &mut [u64], y: &[u64], mask: u64) -> u64 {
+    let mut borrow: u64 = 0;
+    for (x_val, y_val) in x.iter_mut().zip(y.iter()) {
+        let (diff, b) = ct_sbb_u64(*x_val, y_val & mask, borrow);
+        *x_val = diff;
+        borrow = b;
+    }
+    borrow
+}
+
+///Add limb slices, ACC will be set to `acc + addend` and the carry out
+///is returned as `0` or `1`.
+///
+///Every limb is visited and the carry is always propagated through
+///the whole slice.
+///
+///#Panic:
+///
+///This function will panic if the slices are not equal length.
+pub fn ct_add_limbs(acc: &mut [u64], addend: &[u64]) -> u64 {
+    if acc.len() != addend.len() {
+        panic!("Consistent Time: Attempted add between non-equal lens");
+    }
+    add_masked(acc, addend, max!(u64))
+}
+///Subtract limb slices, ACC will be set to `acc - sub` and the borrow
+///out is returned as `0` or `1`.
+///
+///Every limb is visited and the borrow is always propagated through
+///the whole slice.
+///
+///#Panic:
+///
+///This function will panic if the slices are not equal length.
+pub fn ct_sub_limbs(acc: &mut [u64], sub: &[u64]) -> u64 {
+    if acc.len() != sub.len() {
+        panic!("Consistent Time: Attempted sub between non-equal lens");
+    }
+    sub_masked(acc, sub, max!(u64))
+}
+#[test]
+fn test_ct_add_sub_limbs() {
+    let vals: [u128;5] = [0, 1, u64::MAX as u128, 1 << 64, u128::MAX];
+    for &x in vals.iter() {
+        for &y in vals.iter() {
+            let mut r = to_limbs(x);
+            let carry = ct_add_limbs(&mut r, &to_limbs(y));
+            let (sum, c) = x.overflowing_add(y);
+            assert_eq!( r, to_limbs(sum));
+            assert_eq!( carry, c as u64);
+            let mut r = to_limbs(x);
+            let borrow = ct_sub_limbs(&mut r, &to_limbs(y));
+            let (diff, b) = x.overflowing_sub(y);
+            assert_eq!( r, to_limbs(diff));
+            assert_eq!( borrow, b as u64);
+        }
+    }
+}
+#[test]
+#[should_panic]
+fn test_ct_add_limbs_panic() {
+    let mut x: [u64;2] = [0;2];
+    ct_add_limbs(&mut x, &[0;3]);
+}
+
+/*
+ * Multiply and accumulate a single limb, returns the low word and the
+ * carry. a + b * c + carry never overflows a u128.
+ */
+#[inline(always)]
+pub(crate) fn mac(a: u64, b: u64, c: u64, carry: u64) -> (u64, u64) {
+    let t = (a as u128) + (b as u128) * (c as u128) + (carry as u128);
+    (t as u64, (t >> 64) as u64)
+}
+
+///Multiply limb slices, OUT will be set to the full product `a * b`.
+///
+///Schoolbook multiplication, every limb of `a` is multiplied with every
+///limb of `b`. The loops only depend on the lengths and the product
+///never wraps.
+///
+///#Panic:
+///
+///This function will panic if `out` is not `a.len() + b.len()` limbs.
+pub fn ct_mul_limbs(out: &mut [u64], a: &[u64], b: &[u64]) {
+    let n = a.len();
+    if out.len() != n + b.len() {
+        panic!("Consistent Time: Attempted multiply into a mis-sized output");
+    }
+    for t in out.iter_mut() {
+        *t = 0;
+    }
+    for (i, &b_i) in b.iter().enumerate() {
+        let mut carry: u64 = 0;
+        for (t, &a_j) in out[i..i + n].iter_mut().zip(a.iter()) {
+            let (lo, c) = mac(*t, a_j, b_i, carry);
+            *t = lo;
+            carry = c;
+        }
+        out[i + n] = carry;
+    }
+}
+#[test]
+fn test_ct_mul_limbs() {
+    let vals: [u64;5] = [0, 1, 2, 0xDEADBEEFCAFEBABE, u64::MAX];
+    for &x0 in vals.iter() {
+        for &x1 in vals.iter() {
+            for &y in vals.iter() {
+                let mut out = [0u64;3];
+                ct_mul_limbs(&mut out, &[x0, x1], &[y]);
+                let lo = (x0 as u128) * (y as u128);
+                let hi = (x1 as u128) * (y as u128) + (lo >> 64);
+                assert_eq!( out, [lo as u64, hi as u64, (hi >> 64) as u64]);
+            }
+        }
+    }
+    let mut out = [0u64;4];
+    ct_mul_limbs(&mut out, &[u64::MAX, u64::MAX], &[u64::MAX, u64::MAX]);
+    //(2^128 - 1)^2 = 2^256 - 2^129 + 1
+    assert_eq!( out, [1, 0, u64::MAX - 1, u64::MAX]);
+    let mut out = [7u64;4];
+    ct_mul_limbs(&mut out, &[0, 1], &[0, 1]);
+    assert_eq!( out, [0, 0, 1, 0]);
+}
+#[test]
+#[should_panic]
+fn test_ct_mul_limbs_panic() {
+    let mut out = [0u64;3];
+    ct_mul_limbs(&mut out, &[0;2], &[0;2]);
+}
+
+///Modular addition of limb slices, X will be set to `(x + y) mod m`.
+///
+///`x` and `y` must already be reduced, below `m`. The sum is formed,
+///`m` is always subtracted, and `m` is added back under a mask if the
+///subtraction went negative. No temporary buffer is needed.
+///
+///#Panic:
+///
+///This function will panic if the three slices are not equal length.
+pub fn ct_add_mod_limbs(x: &mut [u64], y: &[u64], m: &[u64]) {
+    let len = x.len();
+    if len != y.len() || len != m.len() {
+        panic!("Consistent Time: Attempted modular add between non-equal lens");
+    }
+    let carry = add_masked(x, y, max!(u64));
+    let borrow = sub_masked(x, m, max!(u64));
+    let restore = (carry ^ 1) & borrow;
+    add_masked(x, m, restore.wrapping_neg());
+}
+///Modular subtraction of limb slices, X will be set to `(x - y) mod m`.
+///
+///`x` and `y` must already be reduced, below `m`. `m` is added back
+///under a mask if the difference went negative.
+///
+///#Panic:
+///
+///This function will panic if the three slices are not equal length.
+pub fn ct_sub_mod_limbs(x: &mut [u64], y: &[u64], m: &[u64]) {
+    let len = x.len();
+    if len != y.len() || len != m.len() {
+        panic!("Consistent Time: Attempted modular sub between non-equal lens");
+    }
+    let borrow = sub_masked(x, y, max!(u64));
+    add_masked(x, m, borrow.wrapping_neg());
+}
+#[cfg(test)]
+fn to_limbs(x: u128) -> [u64;2] {
+    [x as u64, (x >> 64) as u64]
+}
+#[test]
+fn test_ct_mod_limbs() {
+    let moduli: [u128;3] = [0xFFFFFFFF_FFFFFFFF_FFFFFFFF_FFFFFF61, 1 << 64, 97];
+    for &m in moduli.iter() {
+        let vals: [u128;5] = [0, 1, m / 2, m - 2, m - 1];
+        for &x in vals.iter() {
+            for &y in vals.iter() {
+                let want_add = if x >= m - y { x - (m - y) } else { x + y };
+                let want_sub = if x >= y { x - y } else { m - (y - x) };
+                let mut r = to_limbs(x);
+                ct_add_mod_limbs(&mut r, &to_limbs(y), &to_limbs(m));
+                assert_eq!( r, to_limbs(want_add));
+                let mut r = to_limbs(x);
+                ct_sub_mod_limbs(&mut r, &to_limbs(y), &to_limbs(m));
+                assert_eq!( r, to_limbs(want_sub));
+            }
+        }
+    }
+}
+#[test]
+#[should_panic]
+fn test_ct_add_mod_limbs_panic() {
+    let mut x: [u64;2] = [0;2];
+    ct_add_mod_limbs(&mut x, &[0;2], &[1]);
+}
+
+/*
+ * Borrow out of x - y without storing the difference, so x < y.
+ */
+fn lt_limbs(x: &[u64], y: &[u64]) -> u64 {
+    let mut borrow: u64 = 0;
+    for (x_val, y_val) in x.iter().zip(y.iter()) {
+        borrow = ct_sbb_u64(*x_val, *y_val, borrow).1;
+    }
+    borrow
+}
+/*
+ * Swap two equal length slices under a mask.
+ */
+fn swap_masked(x: &mut [u64], y: &mut [u64], mask: u64) {
+    for (x_val, y_val) in x.iter_mut().zip(y.iter_mut()) {
+        let t = (*x_val ^ *y_val) & mask;
+        *x_val ^= t;
+        *y_val ^= t;
+    }
+}
+/*
+ * Shift right by a single bit, cheaper than the barrel shifter when
+ * the amount is public.
+ */
+fn shr1(x: &mut [u64]) {
+    let len = x.len();
+    for i in 0..len {
+        let hi = if i + 1 < len { x[i + 1] << 63 } else { 0 };
+        x[i] = (x[i] >> 1) | hi;
+    }
+}
+
+///Greatest common divisor of limb slices, B will be set to `gcd(a, b)`.
+///
+///A is used as scratch space and is clobbered. `gcd(0, 0)` is `0`.
+///This is Stein's binary GCD, the number of iterations only depends
+///on the (public) number of limbs.
+///
+///#Panic:
+///
+///This function will panic if the slices are not equal length.
+pub fn ct_gcd_limbs(a: &mut [u64], b: &mut [u64]) {
+    let len = a.len();
+    if len != b.len() {
+        panic!("Consistent Time: Attempted gcd between non-equal lens");
+    }
+    //common power of two, every limb is visited
+    let mut k: usize = 0;
+    let mut still: usize = 1;
+    for (a_val, b_val) in a.iter().zip(b.iter()) {
+        let both = a_val | b_val;
+        for i in 0..64 {
+            still &= (((both >> i) & 1) ^ 1) as usize;
+            k += still;
+        }
+    }
+    ct_shr_slice(a, k);
+    ct_shr_slice(b, k);
+    //make v odd, u is kept in a and v in b
+    let b_even = (b.first().map_or(0, |x| *x) & 1) ^ 1;
+    swap_masked(a, b, b_even.wrapping_neg());
+    for _ in 0..(2 * 64 * len) {
+        let odd = (a[0] & 1).wrapping_neg();
+        let swap = odd & lt_limbs(a, b).wrapping_neg();
+        swap_masked(a, b, swap);
+        sub_masked(a, b, odd);
+        shr1(a);
+    }
+    ct_shl_slice(b, k);
+}
+#[test]
+fn test_ct_gcd_limbs() {
+    let vals: [u128;7] = [0, 1, 6, 1 << 70, 3 << 90, 0xFFFFFFFF_FFFFFFFF_FFFFFFFF_FFFFFF61, u128::MAX];
+    for &x in vals.iter() {
+        for &y in vals.iter() {
+            let (mut p, mut q) = (x, y);
+            while q != 0 { let t = p % q; p = q; q = t; }
+            let mut a = to_limbs(x);
+            let mut b = to_limbs(y);
+            ct_gcd_limbs(&mut a, &mut b);
+            assert_eq!( b, to_limbs(p));
+        }
+    }
+    let mut a: [u64;0] = [];
+    let mut b: [u64;0] = [];
+    ct_gcd_limbs(&mut a, &mut b);
+}
+
+/*
+ * One folding step for m = 2^k - c, x = hi * 2^k + lo becomes
+ * lo + c * hi in place. Limb j of hi lives at limbs k/64 + j and up,
+ * never below j, so each limb can be overwritten as soon as it has
+ * been read. All the branches are on k and the length, never on x.
+ */
+fn fold_pseudo_mersenne(x: &mut [u64], k: usize, c: u64) {
+    let len = x.len();
+    let kq = k / 64;
+    let kr = (k % 64) as u32;
+    let lo_mask = (1u64 << kr).wrapping_sub(1);
+    let mut carry: u64 = 0;
+    for j in 0..len {
+        let a = if kq + j < len { x[kq + j] } else { 0 };
+        let b = if kq + j + 1 < len { x[kq + j + 1] } else { 0 };
+        let hi = if kr == 0 { a } else { (a >> kr) | (b << (64 - kr)) };
+        let lo = if j < kq { x[j] } else if j == kq { x[j] & lo_mask } else { 0 };
+        let (val, c_out) = mac(lo, hi, c, carry);
+        x[j] = val;
+        carry = c_out;
+    }
+}
+
+///Reduction modulo a pseudo-Mersenne number, OUT will be set to
+///`x mod (2^k - c)`.
+///
+///The high part above bit `k` is repeatedly multiplied by `c` and
+///folded into the low part. The number of folds is worked out from
+///`k`, `c` and the length of `x`, which are all public. The last step
+///adds `c` and keeps the result under a mask if it reached `2^k`.
+///
+///`out` must be exactly the `ceil(k / 64)` limbs needed to hold the
+///modulus, `x` can be any length and is used as scratch space and
+///clobbered. `c` must be below `2^(k - 2)`.
+///
+///#Panic:
+///
+///This function will panic if `out` is the wrong length, if `x` is
+///shorter than `out`, or if `c` is too large.
+pub fn ct_reduce_pseudo_mersenne(out: &mut [u64], x: &mut [u64], k: usize, c: u64) {
+    let n = out.len();
+    if n == 0 || k > 64 * n || k <= 64 * (n - 1) || x.len() < n {
+        panic!("Consistent Time: Attempted pseudo-Mersenne reduction with mis-sized slices");
+    }
+    let c_bits = 64 - c.leading_zeros() as usize;
+    if c_bits + 2 > k {
+        panic!("Consistent Time: Attempted pseudo-Mersenne reduction with a large c");
+    }
+    //x < 2^bits, fold until x < 2^(k + 1) then once more for x < 2^k + c
+    let mut bits = 64 * x.len();
+    while bits > k + 1 {
+        let folded = bits - k + c_bits;
+        bits = if folded > k { folded } else { k } + 1;
+        fold_pseudo_mersenne(x, k, c);
+    }
+    fold_pseudo_mersenne(x, k, c);
+
+    //x + c >= 2^k exactly when x >= m, in that case x - m = x + c - 2^k
+    let mut carry = c;
+    for x_val in x.iter_mut() {
+        let (sum, c_out) = ct_adc_u64(*x_val, carry, 0);
+        *x_val = sum;
+        carry = c_out;
+    }
+    let ge = if k < 64 * x.len() { (x[k / 64] >> (k % 64)) & 1 } else { carry };
+    //bit k is the only one that can be set at or above k, clear it
+    out.copy_from_slice(&x[..n]);
+    if k < 64 * n {
+        out[k / 64] &= !(1u64 << (k % 64));
+    }
+    //otherwise take c back off, this can not wrap
+    let mask = (ge ^ 1).wrapping_neg();
+    let mut borrow = c & mask;
+    for out_val in out.iter_mut() {
+        let (diff, b) = ct_sbb_u64(*out_val, borrow, 0);
+        *out_val = diff;
+        borrow = b;
+    }
+}
+#[test]
+fn test_ct_reduce_pseudo_mersenne() {
+    //single limb moduli, checked against u128 arithmetic
+    let params: [(usize, u64);4] = [(61, 1), (64, 59), (63, 25), (40, 87)];
+    let vals: [u128;6] = [0, 1, u64::MAX as u128, 1 << 100,
+        0xFFFFFFFF_FFFFFFFF_FFFFFFFF_FFFFFF61, u128::MAX];
+    for &(k, c) in params.iter() {
+        let m = (1u128 << k) - (c as u128);
+        for &v in vals.iter() {
+            for &v in [v, m, m - 1, m + 1, 2 * m].iter() {
+                let mut x = to_limbs(v);
+                let mut out = [0u64;1];
+                ct_reduce_pseudo_mersenne(&mut out, &mut x, k, c);
+                assert_eq!( out[0] as u128, v % m);
+            }
+        }
+    }
+    //no room above the modulus, the carry out of adding c decides
+    let mut x = [u64::MAX;1];
+    let mut out = [0u64;1];
+    ct_reduce_pseudo_mersenne(&mut out, &mut x, 64, 59);
+    assert_eq!( out, [58]);
+    //two limb moduli, x = m * q + r is built with ct_mul_limbs
+    let params: [(usize, u64);3] = [(127, 1), (128, 159), (100, 0)];
+    for &(k, c) in params.iter() {
+        let m = ((1u128 << (k - 1)) - (c as u128)) + (1u128 << (k - 1));
+        let m = to_limbs(m);
+        let qs: [u128;3] = [0, 12345, u128::MAX];
+        let rs: [u128;3] = [0, 1, ((m[1] as u128) << 64 | m[0] as u128) - 1];
+        for &q in qs.iter() {
+            for &r in rs.iter() {
+                let mut x = [0u64;4];
+                ct_mul_limbs(&mut x, &m, &to_limbs(q));
+                let mut r4 = [0u64;4];
+                r4[..2].copy_from_slice(&to_limbs(r));
+                ct_add_limbs(&mut x, &r4);
+                let mut out = [0u64;2];
+                ct_reduce_pseudo_mersenne(&mut out, &mut x, k, c);
+                assert_eq!( out, to_limbs(r));
+            }
+        }
+    }
+}
+#[test]
+#[should_panic]
+fn test_ct_reduce_pseudo_mersenne_panic() {
+    let mut x = [0u64;4];
+    let mut out = [0u64;2];
+    ct_reduce_pseudo_mersenne(&mut out, &mut x, 64, 1);
+}
+
+/*
+ * Byte position i, counting from the least significant byte, is byte
+ * i % 8 of limb i / 8. Every conversion walks every byte once in the
+ * same order, a partial top limb is not a special case.
+ */
+macro_rules! ct_from_bytes_gen {
+    ($name:ident,$doc:expr,$index:ident) => {
+        #[doc=$doc]
+        ///
+        ///Bytes past the end of the slice are taken as zero, so a short
+        ///encoding is zero extended.
+        ///
+        ///#Panic:
+        ///
+        ///This function will panic if `bytes` does not fit in `out`.
+        pub fn $name(out: &mut [u64], bytes: &[u8]) {
+            let len = bytes.len();
+            if len > 8 * out.len() {
+                panic!("Consistent Time: Attempted to load bytes into too few limbs");
+            }
+            for x in out.iter_mut() {
+                *x = 0;
+            }
+            for i in 0..len {
+                out[i / 8] |= (bytes[$index(i, len)] as u64) << (8 * (i % 8));
+            }
+        }
+    }
+}
+macro_rules! ct_to_bytes_gen {
+    ($name:ident,$doc:expr,$index:ident) => {
+        #[doc=$doc]
+        ///
+        ///Exactly `out.len()` bytes are written, anything above them in
+        ///`limbs` is dropped.
+        ///
+        ///#Panic:
+        ///
+        ///This function will panic if `out` is longer than `limbs`.
+        pub fn $name(out: &mut [u8], limbs: &[u64]) {
+            let len = out.len();
+            if len > 8 * limbs.len() {
+                panic!("Consistent Time: Attempted to store more bytes than limbs hold");
+            }
+            for i in 0..len {
+                out[$index(i, len)] = (limbs[i / 8] >> (8 * (i % 8))) as u8;
+            }
+        }
+    }
+}
+fn le_index(i: usize, _len: usize) -> usize {
+    i
+}
+fn be_index(i: usize, len: usize) -> usize {
+    len - 1 - i
+}
+ct_from_bytes_gen!(ct_limbs_from_be_bytes,
+    "Load big endian bytes into little endian limbs.", be_index);
+ct_from_bytes_gen!(ct_limbs_from_le_bytes,
+    "Load little endian bytes into little endian limbs.", le_index);
+ct_to_bytes_gen!(ct_limbs_to_be_bytes,
+    "Store little endian limbs as big endian bytes.", be_index);
+ct_to_bytes_gen!(ct_limbs_to_le_bytes,
+    "Store little endian limbs as little endian bytes.", le_index);
+#[test]
+fn test_ct_limbs_bytes() {
+    let base: u128 = 0x0123_4567_89AB_CDEF_FEDC_BA98_7654_3210;
+    let be = base.to_be_bytes();
+    let le = base.to_le_bytes();
+    let mut x = [7u64;2];
+    ct_limbs_from_be_bytes(&mut x, &be);
+    assert_eq!( x, to_limbs(base));
+    ct_limbs_from_le_bytes(&mut x, &le);
+    assert_eq!( x, to_limbs(base));
+    let mut out = [0u8;16];
+    ct_limbs_to_be_bytes(&mut out, &x);
+    assert_eq!( out, be);
+    ct_limbs_to_le_bytes(&mut out, &x);
+    assert_eq!( out, le);
+    //partial limbs
+    let mut x = [7u64;2];
+    ct_limbs_from_be_bytes(&mut x, &be[7..]);
+    assert_eq!( x, to_limbs(base & ((1 << 72) - 1)));
+    ct_limbs_from_le_bytes(&mut x, &le[..9]);
+    assert_eq!( x, to_limbs(base & ((1 << 72) - 1)));
+    let mut out = [0u8;9];
+    ct_limbs_to_be_bytes(&mut out, &x);
+    assert_eq!( out, be[7..]);
+    ct_limbs_to_le_bytes(&mut out, &x);
+    assert_eq!( out, le[..9]);
+    let mut x: [u64;0] = [];
+    ct_limbs_from_be_bytes(&mut x, &[]);
+}
+#[test]
+#[should_panic]
+fn test_ct_limbs_from_bytes_panic() {
+    let mut x = [0u64;1];
+    ct_limbs_from_be_bytes(&mut x, &[0;9]);
+}
+
+///Reduce a 64 byte value modulo a 32 byte modulus, the hash to scalar
+///step of EdDSA like schemes.
+///
+///Both the value and the modulus are little endian, as in Ed25519, and
+///the result is written as 32 little endian bytes. The reduction is
+///bit serial, the remainder is doubled, the next bit of `wide` is
+///shifted in and `m` is subtracted and added back under a mask, for
+///all 512 bits.
+///
+///#Panic:
+///
+///This function will panic if the modulus is zero, the modulus is
+///public.
+pub fn ct_reduce_wide(out: &mut [u8;32], wide: &[u8;64], modulus: &[u8;32]) {
+    if modulus.iter().all(|x| *x == 0) {
+        panic!("Consistent Time: Attempted reduction by a zero modulus");
+    }
+    let mut x = [0u64;8];
+    ct_limbs_from_le_bytes(&mut x, wide);
+    //one spare limb, the doubled remainder can reach 257 bits
+    let mut m = [0u64;5];
+    ct_limbs_from_le_bytes(&mut m[..4], modulus);
+    let mut r = [0u64;5];
+    for i in (0..512).rev() {
+        let bit = (x[i / 64] >> (i % 64)) & 1;
+        let mut top = bit;
+        for r_val in r.iter_mut() {
+            let next = *r_val >> 63;
+            *r_val = (*r_val << 1) | top;
+            top = next;
+        }
+        let borrow = sub_masked(&mut r, &m, max!(u64));
+        add_masked(&mut r, &m, borrow.wrapping_neg());
+    }
+    ct_limbs_to_le_bytes(out, &r[..4]);
+}
+#[test]
+fn test_ct_reduce_wide() {
+    //x = m * q + r, built with ct_mul_limbs and checked both ways
+    let moduli: [[u64;4];3] = [
+        [0x5812631A5CF5D3ED, 0x14DEF9DEA2F79CD6, 0, 0x1000000000000000],
+        [u64::MAX - 188, u64::MAX, u64::MAX, u64::MAX],
+        [12345, 0, 0, 0],
+    ];
+    for m in moduli.iter() {
+        let qs: [[u64;4];3] = [[0;4], [1, 2, 3, 4], [u64::MAX;4]];
+        let mut m_minus_1 = *m;
+        ct_sub_limbs(&mut m_minus_1, &[1, 0, 0, 0]);
+        let rs: [[u64;4];3] = [[0;4], [7, 0, 0, 0], m_minus_1];
+        for q in qs.iter() {
+            for r in rs.iter() {
+                let mut x = [0u64;8];
+                ct_mul_limbs(&mut x, m, q);
+                let mut r8 = [0u64;8];
+                r8[..4].copy_from_slice(r);
+                ct_add_limbs(&mut x, &r8);
+                let mut wide = [0u8;64];
+                ct_limbs_to_le_bytes(&mut wide, &x);
+                let mut m_bytes = [0u8;32];
+                ct_limbs_to_le_bytes(&mut m_bytes, m);
+                let mut out = [0u8;32];
+                ct_reduce_wide(&mut out, &wide, &m_bytes);
+                let mut want = [0u8;32];
+                ct_limbs_to_le_bytes(&mut want, r);
+                assert_eq!( out, want);
+            }
+        }
+    }
+}
+
+///Optional swapping of two fixed size limb arrays.
+///
+///IF flag == True THEN X and Y are exchanged
+///
+///If flag == False THEN both are unchanged
+///
+///The flag is turned into a mask once and both arrays are rewritten
+///with `x ^= (x ^ y) & mask`. The length is a constant so there are
+///no bounds checks, see `ct_cswap_limbs_4` and friends for the
+///unrolled versions.
+pub fn ct_cswap_limbs<const N: usize>(flag: bool, x: &mut [u64; N], y: &mut [u64; N]) {
+    let mask = ct_select_u64(flag, max!(u64), 0);
+    for i in 0..N {
+        let t = (x[i] ^ y[i]) & mask;
+        x[i] ^= t;
+        y[i] ^= t;
+    }
+}
+macro_rules! ct_cswap_limbs_gen {
+    ($name:ident,$n:expr;;$test_name:ident;;$($i:expr),*) => {
+        ///Optional swapping of two limb arrays, fully unrolled.
+        ///
+        ///IF flag == True THEN X and Y are exchanged
+        ///
+        ///If flag == False THEN both are unchanged
+        #[inline]
+        pub fn $name(flag: bool, x: &mut [u64; $n], y: &mut [u64; $n]) {
+            let mask = ct_select_u64(flag, max!(u64), 0);
+            $(
+                let t = (x[$i] ^ y[$i]) & mask;
+                x[$i] ^= t;
+                y[$i] ^= t;
+            )*
+        }
+        #[test]
+        fn $test_name() {
+            let mut x = [0u64; $n];
+            let mut y = [0u64; $n];
+            for i in 0..$n {
+                x[i] = i as u64;
+                y[i] = max!(u64) - (i as u64);
+            }
+            let (x0, y0) = (x, y);
+            $name(false, &mut x, &mut y);
+            assert_eq!( (x, y), (x0, y0));
+            $name(true, &mut x, &mut y);
+            assert_eq!( (x, y), (y0, x0));
+            ct_cswap_limbs(true, &mut x, &mut y);
+            assert_eq!( (x, y), (x0, y0));
+            ct_cswap_limbs(false, &mut x, &mut y);
+            assert_eq!( (x, y), (x0, y0));
+        }
+    }
+}
+ct_cswap_limbs_gen!(ct_cswap_limbs_4,4;;test_ct_cswap_limbs_4;;0,1,2,3);
+ct_cswap_limbs_gen!(ct_cswap_limbs_5,5;;test_ct_cswap_limbs_5;;0,1,2,3,4);
+ct_cswap_limbs_gen!(ct_cswap_limbs_8,8;;test_ct_cswap_limbs_8;;0,1,2,3,4,5,6,7);
@@ -0,0 +1,247 @@
+//Copyright 2016 William Cody Laeder
+//
+//Licensed under the Apache License, Version 2.0 (the "License");
+//you may not use this file except in compliance with the License.
+//you may obtain a copy of the License at
+//
+//  http://www.apache.org/licenses/LICENSE-2.0
+//
+//Unless required by applicable law or agreed to in writing, software
+//distrubuted under the License is distrubuted on as "AS IS" BASIS,
+//WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//See the License for the specific language governing permissions and
+//limitations under the License.
+
+
+//!Montgomery multiplication
+//!
+//!Modular multiplication over little endian limb slices for an odd
+//!modulus `m` of `n` limbs, with `R = 2^(64 * n)`. Numbers are kept
+//!in Montgomery form, `a * R mod m`. The modulus and `n0` are public,
+//!the operands are secret.
+
+use super::ct_select_u64;
+use super::arith::{ct_adc_u64};
+use super::limbs::{add_masked, sub_masked, mac, ct_add_mod_limbs};
+
+///Compute `n0 = -m^-1 mod 2^64` from the lowest limb of the modulus.
+///
+///A fixed six Newton steps of wrapping multiplies and subtractions,
+///nothing depends on the value of `m0`. `m0` must be odd.
+pub fn ct_mont_n0(m0: u64) -> u64 {
+    //Newton iteration, each step doubles the number of correct bits
+    let mut inv: u64 = 1;
+    for _ in 0..6 {
+        inv = inv.wrapping_mul(2u64.wrapping_sub(m0.wrapping_mul(inv)));
+    }
+    inv.wrapping_neg()
+}
+#[test]
+fn test_ct_mont_n0() {
+    let moduli: [u64;4] = [1, 3, 0xFFFFFFFFFFFFFFC5, 0x8000000000000001];
+    for &m in moduli.iter() {
+        assert_eq!( m.wrapping_mul(ct_mont_n0(m)), u64::MAX);
+    }
+}
+
+///Montgomery multiplication, OUT will be set to `a * b * R^-1 mod m`.
+///
+///Coarsely Integrated Operand Scanning (CIOS), one limb of `b` is
+///multiplied in and then one limb is reduced away, so the running
+///value never grows past `n + 2` limbs. The two top limbs live in
+///locals and `out` is the rest, no scratch space is needed.
+///
+///`a` and `b` must be below `m` and `n0` must be `ct_mont_n0(m[0])`.
+///The result is brought below `m` with a masked subtraction.
+///
+///#Panic:
+///
+///This function will panic if the slices are not equal length.
+pub fn ct_mont_mul(out: &mut [u64], a: &[u64], b: &[u64], m: &[u64], n0: u64) {
+    let n = m.len();
+    if n != out.len() || n != a.len() || n != b.len() {
+        panic!("Consistent Time: Attempted Montgomery multiply between non-equal lens");
+    }
+    for t in out.iter_mut() {
+        *t = 0;
+    }
+    let mut top: u64 = 0;
+    for &b_i in b.iter() {
+        let mut carry: u64 = 0;
+        for (t, &a_j) in out.iter_mut().zip(a.iter()) {
+            let (lo, c) = mac(*t, a_j, b_i, carry);
+            *t = lo;
+            carry = c;
+        }
+        let (t_n, t_n1) = ct_adc_u64(top, carry, 0);
+
+        let q = out[0].wrapping_mul(n0);
+        let (_, mut carry) = mac(out[0], q, m[0], 0);
+        for j in 1..n {
+            let (lo, c) = mac(out[j], q, m[j], carry);
+            out[j - 1] = lo;
+            carry = c;
+        }
+        let (lo, c) = ct_adc_u64(t_n, carry, 0);
+        out[n - 1] = lo;
+        top = t_n1 + c;
+    }
+    //the value is below 2m, subtract m and add it back under a mask
+    let borrow = sub_masked(out, m, u64::MAX);
+    let restore = (top ^ 1) & borrow;
+    add_masked(out, m, restore.wrapping_neg());
+}
+#[cfg(test)]
+fn mul_mod_ref(a: &[u64;2], b: &[u64;2], m: &[u64;2]) -> [u64;2] {
+    //double and add from the top bit of b
+    let mut acc = [0u64;2];
+    for i in (0..128).rev() {
+        let t = acc;
+        ct_add_mod_limbs(&mut acc, &t, m);
+        if (b[i / 64] >> (i % 64)) & 1 == 1 {
+            ct_add_mod_limbs(&mut acc, a, m);
+        }
+    }
+    acc
+}
+#[test]
+fn test_ct_mont_mul() {
+    let moduli: [[u64;2];3] = [
+        [0xFFFFFFFFFFFFFF61, 0xFFFFFFFFFFFFFFFF],
+        [0x0000000000000001, 0x8000000000000000],
+        [0x123456789ABCDEF1, 0x0000000000000001],
+    ];
+    for m in moduli.iter() {
+        let n0 = ct_mont_n0(m[0]);
+        //R mod m by doubling one 128 times, then R^2 mod m
+        let mut one_r = [1u64, 0];
+        for _ in 0..128 {
+            let t = one_r;
+            ct_add_mod_limbs(&mut one_r, &t, m);
+        }
+        let r2 = mul_mod_ref(&one_r, &one_r, m);
+        let vals: [[u64;2];4] = [
+            [0, 0],
+            [1, 0],
+            [m[0] - 1, m[1]],
+            [0xDEADBEEFCAFEBABE, m[1] >> 1],
+        ];
+        for a in vals.iter() {
+            for b in vals.iter() {
+                let mut a_m = [0u64;2];
+                let mut b_m = [0u64;2];
+                ct_mont_mul(&mut a_m, a, &r2, m, n0);
+                ct_mont_mul(&mut b_m, b, &r2, m, n0);
+                let mut c_m = [0u64;2];
+                ct_mont_mul(&mut c_m, &a_m, &b_m, m, n0);
+                let mut c = [0u64;2];
+                ct_mont_mul(&mut c, &c_m, &[1, 0], m, n0);
+                assert_eq!( c, mul_mod_ref(a, b, m));
+            }
+        }
+        let mut one = [0u64;2];
+        ct_mont_mul(&mut one, &one_r, &[1, 0], m, n0);
+        assert_eq!( one, [1, 0]);
+    }
+}
+#[test]
+fn test_ct_mont_mul_single_limb() {
+    let m: u64 = 0xFFFFFFFFFFFFFFC5;
+    let n0 = ct_mont_n0(m);
+    let r = ((1u128 << 64) % (m as u128)) as u64;
+    let vals: [u64;4] = [0, 1, 12345, m - 1];
+    for &a in vals.iter() {
+        for &b in vals.iter() {
+            let mut out = [0u64;1];
+            ct_mont_mul(&mut out, &[a], &[b], &[m], n0);
+            let back = ((out[0] as u128) * (r as u128)) % (m as u128);
+            assert_eq!( back, ((a as u128) * (b as u128)) % (m as u128));
+        }
+    }
+}
+#[test]
+#[should_panic]
+fn test_ct_mont_mul_panic() {
+    let mut out = [0u64;2];
+    ct_mont_mul(&mut out, &[0;2], &[0;1], &[1;2], 1);
+}
+
+///Compute `R^2 mod m`, used to move numbers into Montgomery form.
+///
+///`1` is doubled modulo `m` for every bit of `R^2`. The modulus is
+///public, this is only here so callers do not need a division.
+pub fn ct_mont_r2<const N: usize>(m: &[u64; N]) -> [u64; N] {
+    let mut r2 = [0u64; N];
+    if N > 0 {
+        r2[0] = 1;
+    }
+    for _ in 0..(128 * N) {
+        let t = r2;
+        ct_add_mod_limbs(&mut r2, &t, m);
+    }
+    r2
+}
+
+///Modular exponentiation with a secret exponent, OUT will be set to
+///`base^exp mod m`.
+///
+///Square and always multiply, for every bit of `exp` from the top the
+///accumulator is squared and multiplied by the base, and the product
+///is kept or discarded with masks. Every bit costs two Montgomery
+///multiplications and `exp` is always walked in full, its length is
+///public.
+///
+///`base` must be below `m`, `m` must be odd, `n0` must be
+///`ct_mont_n0(m[0])` and `r2` must be `ct_mont_r2(m)`. The exponent is
+///little endian limbs.
+pub fn ct_mod_exp<const N: usize>(out: &mut [u64; N], base: &[u64; N], exp: &[u64],
+    m: &[u64; N], n0: u64, r2: &[u64; N]) {
+    let mut one = [0u64; N];
+    if N > 0 {
+        one[0] = 1;
+    }
+    let mut base_m = [0u64; N];
+    ct_mont_mul(&mut base_m, base, r2, m, n0);
+    let mut acc = [0u64; N];
+    ct_mont_mul(&mut acc, r2, &one, m, n0);
+    let mut t = [0u64; N];
+    for i in (0..(64 * exp.len())).rev() {
+        ct_mont_mul(&mut t, &acc, &acc, m, n0);
+        ct_mont_mul(&mut acc, &t, &base_m, m, n0);
+        let flag = unsafe{::core::mem::transmute::<u8,bool>(((exp[i / 64] >> (i % 64)) & 1) as u8)};
+        for j in 0..N {
+            acc[j] = ct_select_u64(flag, acc[j], t[j]);
+        }
+    }
+    ct_mont_mul(out, &acc, &one, m, n0);
+}
+#[test]
+fn test_ct_mod_exp() {
+    //single limb, checked against square and multiply on u128
+    let m: u64 = 0xFFFFFFFFFFFFFFC5;
+    let n0 = ct_mont_n0(m);
+    let r2 = ct_mont_r2(&[m]);
+    let bases: [u64;4] = [0, 1, 2, 0xDEADBEEF];
+    let exps: [u64;5] = [0, 1, 2, 65537, m - 2];
+    for &b in bases.iter() {
+        for &e in exps.iter() {
+            let mut want: u128 = 1;
+            for i in (0..64).rev() {
+                want = (want * want) % (m as u128);
+                if (e >> i) & 1 == 1 {
+                    want = (want * (b as u128)) % (m as u128);
+                }
+            }
+            let mut out = [0u64;1];
+            ct_mod_exp(&mut out, &[b], &[e], &[m], n0, &r2);
+            assert_eq!( out[0] as u128, want);
+        }
+    }
+    //fermat, a^(p-1) = 1 for the prime 2^127 - 1
+    let p: [u64;2] = [u64::MAX, u64::MAX >> 1];
+    let n0 = ct_mont_n0(p[0]);
+    let r2 = ct_mont_r2(&p);
+    let mut out = [0u64;2];
+    ct_mod_exp(&mut out, &[0x0123456789ABCDEF, 42], &[u64::MAX - 1, u64::MAX >> 1], &p, n0, &r2);
+    assert_eq!( out, [1, 0]);
+}
@@ -0,0 +1,867 @@
+//Copyright 2016 William Cody Laeder
+//
+//Licensed under the Apache License, Version 2.0 (the "License");
+//you may not use this file except in compliance with the License.
+//you may obtain a copy of the License at
+//
+//  http://www.apache.org/licenses/LICENSE-2.0
+//
+//Unless required by applicable law or agreed to in writing, software
+//distrubuted under the License is distrubuted on as "AS IS" BASIS,
+//WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//See the License for the specific language governing permissions and
+//limitations under the License.
+
+
+//!Padding
+//!
+//!Validation and removal of cipher padding without a padding oracle.
+//!Every candidate padding byte is checked whatever the claimed length,
+//!all the failure modes are folded into one flag, and lengths derived
+//!from secret bytes are only ever returned, masked, never branched on.
+
+use super::{ct_u8_eq, ct_usize_lt, ct_select_u8, ct_select_usize, ct_rotate_elements, ct_eq_slice,
+    ct_usize_eq};
+
+/*
+ * Copy the first `len` bytes of `buf` into `out` and zero the rest,
+ * touching every byte. `len` is secret.
+ */
+fn copy_prefix(out: &mut [u8], buf: &[u8], len: usize) {
+    for (i, (o, b)) in out.iter_mut().zip(buf.iter()).enumerate() {
+        *o = ct_select_u8(ct_usize_lt(i, len), *b, 0);
+    }
+}
+
+///Validate PKCS#7 padding, returns if it is valid and the length of
+///the data before it.
+///
+///The pad length is the last byte, it must be in `1..=block_size` and
+///that many trailing bytes must all equal it. The whole last block is
+///checked every time. If the padding is invalid the length is `0`.
+///
+///A `buf` that is empty or not a whole number of blocks is invalid,
+///this only depends on its length.
+///
+///#Panic:
+///
+///This function will panic if `block_size` is not in `1..=255`.
+pub fn ct_pkcs7_unpad(buf: &[u8], block_size: usize) -> (bool, usize) {
+    if block_size == 0 || block_size > 255 {
+        panic!("Consistent Time: Attempted PKCS#7 unpad with a bad block size");
+    }
+    let len = buf.len();
+    if len == 0 || !len.is_multiple_of(block_size) {
+        return (false, 0);
+    }
+    let pad = buf[len - 1];
+    let pad_len = pad as usize;
+    let mut valid = !ct_u8_eq(pad, 0) & !ct_usize_lt(block_size, pad_len);
+    for j in 1..(block_size + 1) {
+        let in_pad = !ct_usize_lt(pad_len, j);
+        valid &= !(in_pad & !ct_u8_eq(buf[len - j], pad));
+    }
+    (valid, ct_select_usize(valid, len.wrapping_sub(pad_len), 0))
+}
+///Validate PKCS#7 padding and obliviously copy the data out.
+///
+///`out` must be as long as `buf`. The data bytes are copied and every
+///byte after them, including all of `out` if the padding is invalid,
+///is zeroed. Every byte is written either way. Returns the same as
+///`ct_pkcs7_unpad`.
+///
+///#Panic:
+///
+///This function will panic if `out` and `buf` are not equal length or
+///if `block_size` is not in `1..=255`.
+pub fn ct_pkcs7_unpad_into(out: &mut [u8], buf: &[u8], block_size: usize) -> (bool, usize) {
+    if out.len() != buf.len() {
+        panic!("Consistent Time: Attempted PKCS#7 unpad between non-equal lens");
+    }
+    let (valid, len) = ct_pkcs7_unpad(buf, block_size);
+    copy_prefix(out, buf, len);
+    (valid, len)
+}
+#[test]
+fn test_ct_pkcs7_unpad() {
+    let mut buf = [0xAAu8;16];
+    for pad in 1..17 {
+        for i in (16 - pad)..16 {
+            buf[i] = pad as u8;
+        }
+        assert_eq!( ct_pkcs7_unpad(&buf, 16), (true, 16 - pad));
+        let mut out = [0xFFu8;16];
+        assert_eq!( ct_pkcs7_unpad_into(&mut out, &buf, 16), (true, 16 - pad));
+        for i in 0..16 {
+            assert_eq!( out[i], if i < 16 - pad { 0xAA } else { 0 });
+        }
+    }
+    let bad: [&[u8];5] = [
+        &[0xAA,0xAA,0xAA,0x00],
+        &[0xAA,0xAA,0x01,0x02],
+        &[0xAA,0x03,0x02,0x03],
+        &[0x05,0x05,0x05,0x05],
+        &[0xAA,0xAA,0xAA],
+    ];
+    for b in bad.iter() {
+        assert_eq!( ct_pkcs7_unpad(b, 4), (false, 0));
+        let mut out = [0xFFu8;4];
+        let len = b.len();
+        assert_eq!( ct_pkcs7_unpad_into(&mut out[..len], b, 4), (false, 0));
+        assert_eq!( &out[..len], &[0u8;4][..len]);
+    }
+    //the pad may not exceed the block even if the buffer is longer
+    assert_eq!( ct_pkcs7_unpad(&[0x05;8], 4), (false, 0));
+    assert_eq!( ct_pkcs7_unpad(&[], 4), (false, 0));
+}
+#[test]
+#[should_panic]
+fn test_ct_pkcs7_unpad_panic() {
+    ct_pkcs7_unpad(&[1], 256);
+}
+
+/*
+ * Length of the padded buffer for `data_len` bytes, there is always
+ * at least one byte of padding. Both the length and the block size
+ * are public.
+ */
+fn padded_len(out: &[u8], data_len: usize, block_size: usize) -> usize {
+    if block_size == 0 || block_size > 255 {
+        panic!("Consistent Time: Attempted to pad with a bad block size");
+    }
+    let len = (data_len / block_size + 1) * block_size;
+    if out.len() < len {
+        panic!("Consistent Time: Attempted to pad into a short buffer");
+    }
+    len
+}
+
+///Validate ISO/IEC 7816-4 padding, a `0x80` byte followed by zeros,
+///returns if it is valid and the length of the data before it.
+///
+///The last block is scanned from the end for the first non-zero byte,
+///which has to be `0x80`. Every byte of the last block is checked
+///whatever the padding length is. If the padding is invalid the
+///length is `0`.
+///
+///A `buf` that is empty or not a whole number of blocks is invalid,
+///this only depends on its length.
+///
+///#Panic:
+///
+///This function will panic if `block_size` is not in `1..=255`.
+pub fn ct_iso7816_unpad(buf: &[u8], block_size: usize) -> (bool, usize) {
+    if block_size == 0 || block_size > 255 {
+        panic!("Consistent Time: Attempted ISO 7816-4 unpad with a bad block size");
+    }
+    let len = buf.len();
+    if len == 0 || !len.is_multiple_of(block_size) {
+        return (false, 0);
+    }
+    let mut found = false;
+    let mut valid = false;
+    let mut pad_len: usize = 0;
+    for j in 1..(block_size + 1) {
+        let b = buf[len - j];
+        let first = !ct_u8_eq(b, 0) & !found;
+        valid |= first & ct_u8_eq(b, 0x80);
+        pad_len = ct_select_usize(first, j, pad_len);
+        found |= first;
+    }
+    (valid, ct_select_usize(valid, len - pad_len, 0))
+}
+///Validate ISO/IEC 7816-4 padding and obliviously copy the data out.
+///
+///Works like `ct_pkcs7_unpad_into`.
+///
+///#Panic:
+///
+///This function will panic if `out` and `buf` are not equal length or
+///if `block_size` is not in `1..=255`.
+pub fn ct_iso7816_unpad_into(out: &mut [u8], buf: &[u8], block_size: usize) -> (bool, usize) {
+    if out.len() != buf.len() {
+        panic!("Consistent Time: Attempted ISO 7816-4 unpad between non-equal lens");
+    }
+    let (valid, len) = ct_iso7816_unpad(buf, block_size);
+    copy_prefix(out, buf, len);
+    (valid, len)
+}
+///Apply ISO/IEC 7816-4 padding, returns the padded length.
+///
+///`data` is copied to the front of `out` and followed by `0x80` and
+///as many zeros as are needed to end on a block boundary. The data
+///length is public, it is the ciphertext length.
+///
+///#Panic:
+///
+///This function will panic if `block_size` is not in `1..=255` or
+///`out` can not hold the padded data.
+pub fn ct_iso7816_pad(out: &mut [u8], data: &[u8], block_size: usize) -> usize {
+    let len = padded_len(out, data.len(), block_size);
+    out[..data.len()].copy_from_slice(data);
+    out[data.len()] = 0x80;
+    for o in out[(data.len() + 1)..len].iter_mut() {
+        *o = 0;
+    }
+    len
+}
+#[test]
+fn test_ct_iso7816_unpad() {
+    let data = [0x80u8, 0x00, 0x11, 0x80, 0x22, 0x00, 0x33];
+    for data_len in 0..8 {
+        let mut buf = [0xFFu8;16];
+        let len = ct_iso7816_pad(&mut buf, &data[..data_len], 8);
+        assert_eq!( len, 8);
+        assert_eq!( ct_iso7816_unpad(&buf[..len], 8), (true, data_len));
+        let mut out = [0xFFu8;8];
+        assert_eq!( ct_iso7816_unpad_into(&mut out, &buf[..len], 8), (true, data_len));
+        assert_eq!( &out[..data_len], &data[..data_len]);
+        assert_eq!( &out[data_len..], &[0u8;8][data_len..]);
+    }
+    let mut buf = [0u8;16];
+    assert_eq!( ct_iso7816_pad(&mut buf, &[1;8], 8), 16);
+    assert_eq!( ct_iso7816_unpad(&buf, 8), (true, 8));
+    let bad: [&[u8];5] = [
+        &[0xAA,0xAA,0xAA,0x00],
+        &[0x00,0x00,0x00,0x00],
+        &[0xAA,0xAA,0x80,0x01],
+        &[0xAA,0x81,0x00,0x00],
+        &[0xAA,0xAA,0x80],
+    ];
+    for b in bad.iter() {
+        assert_eq!( ct_iso7816_unpad(b, 4), (false, 0));
+    }
+    //the padding may not reach past the last block
+    assert_eq!( ct_iso7816_unpad(&[0x80,0,0,0,0,0,0,0], 4), (false, 0));
+}
+
+///Validate ANSI X9.23 padding, zeros followed by a length byte,
+///returns if it is valid and the length of the data before it.
+///
+///The same as `ct_pkcs7_unpad` except that the bytes before the
+///length byte must be zero.
+///
+///#Panic:
+///
+///This function will panic if `block_size` is not in `1..=255`.
+pub fn ct_x923_unpad(buf: &[u8], block_size: usize) -> (bool, usize) {
+    if block_size == 0 || block_size > 255 {
+        panic!("Consistent Time: Attempted X9.23 unpad with a bad block size");
+    }
+    let len = buf.len();
+    if len == 0 || !len.is_multiple_of(block_size) {
+        return (false, 0);
+    }
+    let pad = buf[len - 1];
+    let pad_len = pad as usize;
+    let mut valid = !ct_u8_eq(pad, 0) & !ct_usize_lt(block_size, pad_len);
+    for j in 2..(block_size + 1) {
+        let in_pad = !ct_usize_lt(pad_len, j);
+        valid &= !(in_pad & !ct_u8_eq(buf[len - j], 0));
+    }
+    (valid, ct_select_usize(valid, len.wrapping_sub(pad_len), 0))
+}
+///Validate ANSI X9.23 padding and obliviously copy the data out.
+///
+///Works like `ct_pkcs7_unpad_into`.
+///
+///#Panic:
+///
+///This function will panic if `out` and `buf` are not equal length or
+///if `block_size` is not in `1..=255`.
+pub fn ct_x923_unpad_into(out: &mut [u8], buf: &[u8], block_size: usize) -> (bool, usize) {
+    if out.len() != buf.len() {
+        panic!("Consistent Time: Attempted X9.23 unpad between non-equal lens");
+    }
+    let (valid, len) = ct_x923_unpad(buf, block_size);
+    copy_prefix(out, buf, len);
+    (valid, len)
+}
+///Apply ANSI X9.23 padding, returns the padded length.
+///
+///`data` is copied to the front of `out` and followed by zeros and a
+///final byte holding the padding length, ending on a block boundary.
+///
+///#Panic:
+///
+///This function will panic if `block_size` is not in `1..=255` or
+///`out` can not hold the padded data.
+pub fn ct_x923_pad(out: &mut [u8], data: &[u8], block_size: usize) -> usize {
+    let len = padded_len(out, data.len(), block_size);
+    out[..data.len()].copy_from_slice(data);
+    for o in out[data.len()..len].iter_mut() {
+        *o = 0;
+    }
+    out[len - 1] = (len - data.len()) as u8;
+    len
+}
+#[test]
+fn test_ct_x923_unpad() {
+    let data = [0x00u8, 0x01, 0x02, 0x00, 0x04, 0x05, 0x06, 0x07];
+    for data_len in 0..9 {
+        let mut buf = [0xFFu8;16];
+        let len = ct_x923_pad(&mut buf, &data[..data_len], 8);
+        assert_eq!( len, if data_len < 8 { 8 } else { 16 });
+        assert_eq!( ct_x923_unpad(&buf[..len], 8), (true, data_len));
+        let mut out = [0xFFu8;16];
+        assert_eq!( ct_x923_unpad_into(&mut out[..len], &buf[..len], 8), (true, data_len));
+        assert_eq!( &out[..data_len], &data[..data_len]);
+        assert_eq!( &out[data_len..len], &[0u8;16][data_len..len]);
+    }
+    let bad: [&[u8];5] = [
+        &[0xAA,0xAA,0xAA,0x00],
+        &[0xAA,0xAA,0x01,0x02],
+        &[0xAA,0x00,0x03,0x03],
+        &[0x00,0x00,0x00,0x05],
+        &[0xAA,0xAA,0x01],
+    ];
+    for b in bad.iter() {
+        assert_eq!( ct_x923_unpad(b, 4), (false, 0));
+    }
+    assert_eq!( ct_x923_unpad(&[0,0,0,0,0,0,0,8], 4), (false, 0));
+}
+#[test]
+#[should_panic]
+fn test_ct_x923_pad_panic() {
+    let mut out = [0u8;8];
+    ct_x923_pad(&mut out, &[0;8], 8);
+}
+
+///Block cipher padding scheme, with the block size in bytes.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum PaddingScheme {
+    ///PKCS#7, every padding byte holds the padding length.
+    Pkcs7(usize),
+    ///ISO/IEC 7816-4, `0x80` followed by zeros.
+    Iso7816(usize),
+    ///ANSI X9.23, zeros followed by the padding length.
+    X923(usize),
+}
+
+///Validate padding and obliviously copy the data into `dst`.
+///
+///All of `dst` is written whatever the outcome, the data bytes first
+///and zeros after them, so the copy never waits on the validity flag.
+///`dst` may be any length, data that does not fit in it makes the
+///result invalid. Returns if the padding was valid and the masked
+///data length, `(false, 0)` on any failure.
+///
+///#Panic:
+///
+///This function will panic if the block size is not in `1..=255`.
+pub fn ct_unpad_into(buf: &[u8], scheme: PaddingScheme, dst: &mut [u8]) -> (bool, usize) {
+    let (valid, len) = match scheme {
+        PaddingScheme::Pkcs7(block_size) => ct_pkcs7_unpad(buf, block_size),
+        PaddingScheme::Iso7816(block_size) => ct_iso7816_unpad(buf, block_size),
+        PaddingScheme::X923(block_size) => ct_x923_unpad(buf, block_size),
+    };
+    let valid = valid & !ct_usize_lt(dst.len(), len);
+    let len = ct_select_usize(valid, len, 0);
+    for (i, d) in dst.iter_mut().enumerate() {
+        let b = if i < buf.len() { buf[i] } else { 0 };
+        *d = ct_select_u8(ct_usize_lt(i, len), b, 0);
+    }
+    (valid, len)
+}
+#[test]
+fn test_ct_unpad_into() {
+    let schemes = [PaddingScheme::Pkcs7(8), PaddingScheme::Iso7816(8), PaddingScheme::X923(8)];
+    let padded: [[u8;8];3] = [
+        [1,2,3,4,5,3,3,3],
+        [1,2,3,4,5,0x80,0,0],
+        [1,2,3,4,5,0,0,3],
+    ];
+    for (&scheme, buf) in schemes.iter().zip(padded.iter()) {
+        let mut dst = [0xFFu8;12];
+        assert_eq!( ct_unpad_into(buf, scheme, &mut dst), (true, 5));
+        assert_eq!( dst, [1,2,3,4,5,0,0,0,0,0,0,0]);
+        let mut dst = [0xFFu8;5];
+        assert_eq!( ct_unpad_into(buf, scheme, &mut dst), (true, 5));
+        assert_eq!( dst, [1,2,3,4,5]);
+        //the data does not fit
+        let mut dst = [0xFFu8;4];
+        assert_eq!( ct_unpad_into(buf, scheme, &mut dst), (false, 0));
+        assert_eq!( dst, [0;4]);
+        let mut bad = *buf;
+        bad[6] ^= 0x40;
+        let mut dst = [0xFFu8;8];
+        assert_eq!( ct_unpad_into(&bad, scheme, &mut dst), (false, 0));
+        assert_eq!( dst, [0;8]);
+    }
+}
+
+///Remove EME-PKCS1-v1_5 encryption padding, RFC 8017 section 7.2.2.
+///
+///`em` is the decrypted block `0x00 || 0x02 || PS || 0x00 || M` where
+///PS is at least 8 non-zero bytes. The header is checked, the whole
+///block is scanned for the first zero after it with the index kept
+///under masks, and the message is moved to the front of `out` by a
+///barrel rotation, so neither the separator position nor the message
+///length change the memory access pattern.
+///
+///Returns if the padding was valid and the message length. Every
+///failure gives `(false, 0)` and an all zero `out`. This is the
+///Bleichenbacher countermeasure at the padding level, the caller must
+///still not let the result change what it does next, e.g. by
+///continuing with a random key on failure.
+///
+///#Panic:
+///
+///This function will panic if `out` and `em` are not equal length.
+pub fn ct_pkcs1_v15_unpad(out: &mut [u8], em: &[u8]) -> (bool, usize) {
+    let k = em.len();
+    if out.len() != k {
+        panic!("Consistent Time: Attempted PKCS#1 unpad between non-equal lens");
+    }
+    if k < 11 {
+        for o in out.iter_mut() {
+            *o = 0;
+        }
+        return (false, 0);
+    }
+    let mut found = false;
+    let mut sep: usize = 0;
+    for i in 2..k {
+        let is_zero = ct_u8_eq(em[i], 0);
+        sep = ct_select_usize(is_zero & !found, i, sep);
+        found |= is_zero;
+    }
+    let valid = ct_u8_eq(em[0], 0) & ct_u8_eq(em[1], 2) & found & !ct_usize_lt(sep, 10);
+    let len = ct_select_usize(valid, k - sep - 1, 0);
+    out.copy_from_slice(em);
+    ct_rotate_elements(out, sep + 1);
+    for (i, o) in out.iter_mut().enumerate() {
+        *o = ct_select_u8(ct_usize_lt(i, len), *o, 0);
+    }
+    (valid, len)
+}
+#[test]
+fn test_ct_pkcs1_v15_unpad() {
+    let mut em = [0u8;32];
+    em[1] = 2;
+    for i in 2..32 {
+        em[i] = 0x11;
+    }
+    //separator at every legal position
+    for sep in 10..32 {
+        let mut e = em;
+        e[sep] = 0;
+        for i in (sep + 1)..32 {
+            e[i] = i as u8;
+        }
+        let mut out = [0xFFu8;32];
+        let (ok, len) = ct_pkcs1_v15_unpad(&mut out, &e);
+        assert_eq!( (ok, len), (true, 31 - sep));
+        assert_eq!( &out[..len], &e[(sep + 1)..]);
+        assert_eq!( &out[len..], &[0u8;32][len..]);
+    }
+    //a zero inside of the message is fine, only the first one counts
+    let mut e = em;
+    e[12] = 0;
+    e[20] = 0;
+    let mut out = [0u8;32];
+    assert_eq!( ct_pkcs1_v15_unpad(&mut out, &e), (true, 19));
+    let mut bad = [em;5];
+    bad[0][0] = 1;
+    bad[1][1] = 1;
+    bad[2][9] = 0;
+    bad[3][2] = 0;
+    bad[0][20] = 0;
+    bad[1][20] = 0;
+    bad[2][20] = 0;
+    bad[3][20] = 0;
+    for b in bad.iter() {
+        let mut out = [0xFFu8;32];
+        assert_eq!( ct_pkcs1_v15_unpad(&mut out, b), (false, 0));
+        assert_eq!( out, [0u8;32]);
+    }
+    let mut out = [0u8;10];
+    assert_eq!( ct_pkcs1_v15_unpad(&mut out, &[0,2,1,1,1,1,1,1,1,0]), (false, 0));
+}
+
+///DigestInfo prefix for SHA-1, RFC 8017 section 9.2 note 1.
+pub const DIGEST_INFO_SHA1: [u8;15] = [0x30,0x21,0x30,0x09,0x06,0x05,0x2b,0x0e,
+    0x03,0x02,0x1a,0x05,0x00,0x04,0x14];
+///DigestInfo prefix for SHA-256.
+pub const DIGEST_INFO_SHA256: [u8;19] = [0x30,0x31,0x30,0x0d,0x06,0x09,0x60,0x86,
+    0x48,0x01,0x65,0x03,0x04,0x02,0x01,0x05,0x00,0x04,0x20];
+///DigestInfo prefix for SHA-384.
+pub const DIGEST_INFO_SHA384: [u8;19] = [0x30,0x41,0x30,0x0d,0x06,0x09,0x60,0x86,
+    0x48,0x01,0x65,0x03,0x04,0x02,0x02,0x05,0x00,0x04,0x30];
+///DigestInfo prefix for SHA-512.
+pub const DIGEST_INFO_SHA512: [u8;19] = [0x30,0x51,0x30,0x0d,0x06,0x09,0x60,0x86,
+    0x48,0x01,0x65,0x03,0x04,0x02,0x03,0x05,0x00,0x04,0x40];
+
+///Verify an EMSA-PKCS1-v1_5 encoded message, RFC 8017 section 9.2.
+///
+///`em` is the signature representative after the RSA public operation,
+///it must be exactly `0x00 || 0x01 || 0xFF.. || 0x00 || digest_info
+///|| hash` with at least 8 `0xFF` bytes. Every byte is compared
+///against the byte the structure puts at that position and the
+///differences are OR'd together, so which byte mismatched, and if it
+///was the padding, the DigestInfo or the hash, is not revealed. The
+///layout only depends on the lengths, which are public.
+///
+///Returns `false` without comparing if `em` is too short to hold the
+///structure.
+pub fn ct_emsa_pkcs1_v15_verify(em: &[u8], digest_info: &[u8], hash: &[u8]) -> bool {
+    let k = em.len();
+    let t_len = digest_info.len() + hash.len();
+    if k < t_len + 11 {
+        return false;
+    }
+    let ps_end = k - t_len - 1;
+    let hash_start = k - hash.len();
+    let mut flag: u8 = em[0];
+    flag |= em[1] ^ 0x01;
+    for &b in em[2..ps_end].iter() {
+        flag |= b ^ 0xFF;
+    }
+    flag |= em[ps_end];
+    for (&b, &d) in em[(ps_end + 1)..hash_start].iter().zip(digest_info.iter()) {
+        flag |= b ^ d;
+    }
+    for (&b, &h) in em[hash_start..].iter().zip(hash.iter()) {
+        flag |= b ^ h;
+    }
+    ct_u8_eq(flag, 0)
+}
+#[test]
+fn test_ct_emsa_pkcs1_v15_verify() {
+    let hash = [0x42u8;32];
+    let mut em = [0xFFu8;128];
+    em[0] = 0;
+    em[1] = 1;
+    em[128 - 52] = 0;
+    em[(128 - 51)..(128 - 32)].copy_from_slice(&DIGEST_INFO_SHA256);
+    em[(128 - 32)..].copy_from_slice(&hash);
+    assert_eq!( ct_emsa_pkcs1_v15_verify(&em, &DIGEST_INFO_SHA256, &hash), true);
+    for i in 0..128 {
+        let mut bad = em;
+        bad[i] ^= 0x01;
+        assert_eq!( ct_emsa_pkcs1_v15_verify(&bad, &DIGEST_INFO_SHA256, &hash), false);
+    }
+    let mut other = hash;
+    other[0] = 0;
+    assert_eq!( ct_emsa_pkcs1_v15_verify(&em, &DIGEST_INFO_SHA256, &other), false);
+    assert_eq!( ct_emsa_pkcs1_v15_verify(&em, &DIGEST_INFO_SHA384, &hash), false);
+    //fewer than 8 bytes of 0xFF
+    assert_eq!( ct_emsa_pkcs1_v15_verify(&em[..61], &DIGEST_INFO_SHA256, &hash), false);
+    //the lengths of the parts are fixed, a shifted layout fails
+    assert_eq!( ct_emsa_pkcs1_v15_verify(&em, &DIGEST_INFO_SHA256[..18], &hash), false);
+}
+
+///Undo the two OAEP masks in place, RFC 8017 section 7.1.2 step 3.
+///
+///`em` is `Y || maskedSeed || maskedDB` with a `h_len` byte seed. The
+///mask generation function is the caller's, `mgf(seed, buf)` must XOR
+///`MGF(seed, buf.len())` into `buf`. It is called once to recover the
+///seed from the masked DB and once to recover DB from the seed, both
+///over the whole of their inputs, so it only has to be constant time
+///in the data it hashes, which any real hash is.
+///
+///#Panic:
+///
+///This function will panic if `em` is shorter than `2 * h_len + 2`.
+pub fn ct_oaep_unmask<F>(em: &mut [u8], h_len: usize, mut mgf: F)
+  where F: FnMut(&[u8], &mut [u8]) {
+    if em.len() < 2 * h_len + 2 {
+        panic!("Consistent Time: Attempted OAEP unmask of a short block");
+    }
+    let (seed, db) = em[1..].split_at_mut(h_len);
+    mgf(db, seed);
+    mgf(seed, db);
+}
+
+///Locate the `0x01` separator in the unmasked `PS || 0x01 || M` part
+///of DB, the bytes after lHash.
+///
+///Every byte is looked at. The first non-zero byte is the separator
+///and it has to be `0x01`. Returns if it was found and the index the
+///message starts at, `0` if it was not.
+pub fn ct_oaep_find_separator(ps_m: &[u8]) -> (bool, usize) {
+    let mut found = false;
+    let mut valid = false;
+    let mut start: usize = 0;
+    for (i, &b) in ps_m.iter().enumerate() {
+        let first = !ct_u8_eq(b, 0) & !found;
+        valid |= first & ct_u8_eq(b, 1);
+        start = ct_select_usize(first, i + 1, start);
+        found |= first;
+    }
+    (valid, ct_select_usize(valid, start, 0))
+}
+
+///Decode an RSAES-OAEP block, RFC 8017 section 7.1.2.
+///
+///`em` is unmasked in place with `ct_oaep_unmask`, then the leading
+///zero byte, lHash and the separator are all checked and folded into
+///one flag before anything is returned. `l_hash` is the hash of the
+///label and gives `h_len`. The message is moved to the front of `out`
+///by a barrel rotation and the rest of `out` is zeroed.
+///
+///Returns if the block was valid and the message length. Every failure
+///gives `(false, 0)` and an all zero `out`, RFC 8017 requires that the
+///failures can not be told apart.
+///
+///#Panic:
+///
+///This function will panic if `out` and `em` are not equal length or
+///`em` is shorter than `2 * l_hash.len() + 2`.
+pub fn ct_oaep_decode<F>(out: &mut [u8], em: &mut [u8], l_hash: &[u8], mgf: F) -> (bool, usize)
+  where F: FnMut(&[u8], &mut [u8]) {
+    let k = em.len();
+    let h_len = l_hash.len();
+    if out.len() != k {
+        panic!("Consistent Time: Attempted OAEP decode between non-equal lens");
+    }
+    ct_oaep_unmask(em, h_len, mgf);
+    let db = 1 + h_len;
+    let (sep_ok, start) = ct_oaep_find_separator(&em[(db + h_len)..]);
+    let valid = ct_u8_eq(em[0], 0) & ct_eq_slice(&em[db..(db + h_len)], l_hash) & sep_ok;
+    let start = db + h_len + start;
+    let len = ct_select_usize(valid, k - start, 0);
+    out.copy_from_slice(em);
+    ct_rotate_elements(out, start);
+    for (i, o) in out.iter_mut().enumerate() {
+        *o = ct_select_u8(ct_usize_lt(i, len), *o, 0);
+    }
+    (valid, len)
+}
+#[cfg(test)]
+fn toy_mgf(seed: &[u8], buf: &mut [u8]) {
+    let mut acc: u8 = 0x5A;
+    for &s in seed.iter() {
+        acc = acc.rotate_left(3) ^ s;
+    }
+    for (i, b) in buf.iter_mut().enumerate() {
+        *b ^= acc.wrapping_add((i as u8).wrapping_mul(31));
+    }
+}
+#[cfg(test)]
+fn toy_oaep_encode(em: &mut [u8], l_hash: &[u8], msg: &[u8]) {
+    let h_len = l_hash.len();
+    let k = em.len();
+    for e in em.iter_mut() {
+        *e = 0;
+    }
+    for i in 0..h_len {
+        em[1 + i] = (i as u8) ^ 0x3C;
+        em[1 + h_len + i] = l_hash[i];
+    }
+    em[k - msg.len() - 1] = 1;
+    em[(k - msg.len())..].copy_from_slice(msg);
+    let (seed, db) = em[1..].split_at_mut(h_len);
+    toy_mgf(seed, db);
+    toy_mgf(db, seed);
+}
+#[test]
+fn test_ct_oaep_find_separator() {
+    assert_eq!( ct_oaep_find_separator(&[0,0,1,7,0]), (true, 3));
+    assert_eq!( ct_oaep_find_separator(&[1]), (true, 1));
+    assert_eq!( ct_oaep_find_separator(&[0,0,2,1,7]), (false, 0));
+    assert_eq!( ct_oaep_find_separator(&[0,0,0]), (false, 0));
+    assert_eq!( ct_oaep_find_separator(&[]), (false, 0));
+}
+#[test]
+fn test_ct_oaep_decode() {
+    let l_hash = [0xE3u8;8];
+    for msg_len in 0..15 {
+        let msg = [0x00u8, 0x01, 0x02, 0x7F, 0x80, 0xFF, 0x00, 0x01,
+            0x55, 0xAA, 0x10, 0x20, 0x30, 0x40, 0x50];
+        let mut em = [0u8;32];
+        toy_oaep_encode(&mut em, &l_hash, &msg[..msg_len]);
+        let mut out = [0xFFu8;32];
+        assert_eq!( ct_oaep_decode(&mut out, &mut em, &l_hash, toy_mgf), (true, msg_len));
+        assert_eq!( &out[..msg_len], &msg[..msg_len]);
+        assert_eq!( &out[msg_len..], &[0u8;32][msg_len..]);
+    }
+    //leading byte, lHash and separator failures all look the same
+    let mut good = [0u8;32];
+    toy_oaep_encode(&mut good, &l_hash, &[9, 9, 9]);
+    let mut bad = [good;3];
+    bad[0][0] = 1;
+    let mut other_hash = l_hash;
+    other_hash[7] ^= 1;
+    toy_oaep_encode(&mut bad[1], &other_hash, &[9, 9, 9]);
+    ct_oaep_unmask(&mut bad[2], 8, toy_mgf);
+    bad[2][28] = 2;
+    let (seed, db) = bad[2][1..].split_at_mut(8);
+    toy_mgf(seed, db);
+    toy_mgf(db, seed);
+    for b in bad.iter_mut() {
+        let mut out = [0xFFu8;32];
+        assert_eq!( ct_oaep_decode(&mut out, b, &l_hash, toy_mgf), (false, 0));
+        assert_eq!( out, [0u8;32]);
+    }
+}
+#[test]
+#[should_panic]
+fn test_ct_oaep_decode_panic() {
+    let mut em = [0u8;17];
+    let mut out = [0u8;17];
+    ct_oaep_decode(&mut out, &mut em, &[0u8;8], toy_mgf);
+}
+
+///Bytes of TLS record header covered by the MAC, the sequence number,
+///content type, version and length.
+pub const TLS_MAC_HEADER_LEN: usize = 13;
+
+///MAC of a TLS CBC cipher suite.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum TlsCbcMac {
+    ///HMAC-SHA1, 20 byte tag.
+    HmacSha1,
+    ///HMAC-SHA256, 32 byte tag.
+    HmacSha256,
+    ///HMAC-SHA384, 48 byte tag.
+    HmacSha384,
+}
+impl TlsCbcMac {
+    ///Length of the tag.
+    pub fn mac_len(self) -> usize {
+        match self {
+            TlsCbcMac::HmacSha1 => 20,
+            TlsCbcMac::HmacSha256 => 32,
+            TlsCbcMac::HmacSha384 => 48,
+        }
+    }
+    /* log2 of the hash block length */
+    fn block_shift(self) -> u32 {
+        match self {
+            TlsCbcMac::HmacSha384 => 7,
+            _ => 6,
+        }
+    }
+    /* bytes of the message length field in the final hash block */
+    fn length_len(self) -> usize {
+        match self {
+            TlsCbcMac::HmacSha384 => 16,
+            _ => 8,
+        }
+    }
+    /*
+     * Compression function calls of the inner hash for `data_len`
+     * bytes of record data. The key block, the header, the data, the
+     * 0x80 byte and the length field. A shift, not a division, as
+     * `data_len` is secret.
+     */
+    fn inner_blocks(self, data_len: usize) -> usize {
+        let block = 1 << self.block_shift();
+        (block + TLS_MAC_HEADER_LEN + data_len + 1 + self.length_len() + block - 1)
+            >> self.block_shift()
+    }
+}
+
+///Check the padding and MAC of a decrypted TLS CBC record without the
+///Lucky 13 timing leak, RFC 5246 section 6.2.3.2.
+///
+///`record` is `data || MAC || padding` with the IV removed, the last
+///byte gives the padding length and every padding byte must equal it.
+///Up to 256 padding bytes are checked whatever the claimed length. A
+///bad padding is treated as no padding, so a MAC is computed either
+///way, as RFC 5246 asks.
+///
+///`mac(data_len, tag)` must write the HMAC of the header, with the
+///given length, and `record[..data_len]` into `tag`. `data_len` is
+///secret and the hash will run a different number of compression
+///function calls for each value, so `dummy(n)` is called after it
+///with the number of extra compression calls needed to reach the
+///count for the longest possible record. The caller must make
+///`dummy(n)` cost the same as `n` real calls, and must only loop on
+///`n` where that is acceptable, the sum is what is constant.
+///
+///The received tag is pulled out of the last `mac_len + 256` bytes by
+///touching all of them for each tag byte and compared to the computed
+///one. Returns if the record was valid and the data length, `(false,
+///0)` for any failure.
+pub fn ct_tls_cbc_verify<M, D>(record: &[u8], kind: TlsCbcMac, mut mac: M, mut dummy: D) -> (bool, usize)
+  where M: FnMut(usize, &mut [u8]),
+        D: FnMut(usize) {
+    let len = record.len();
+    let mac_len = kind.mac_len();
+    if len < mac_len + 1 {
+        return (false, 0);
+    }
+    let pad = record[len - 1];
+    let pad_len = pad as usize + 1;
+    let mut pad_ok = !ct_usize_lt(len, mac_len + pad_len);
+    let scan = if len < 256 { len } else { 256 };
+    for j in 1..(scan + 1) {
+        let in_pad = !ct_usize_lt(pad_len, j);
+        pad_ok &= !(in_pad & !ct_u8_eq(record[len - j], pad));
+    }
+    let data_len = len - mac_len - ct_select_usize(pad_ok, pad_len, 0);
+
+    let mut received = [0u8;48];
+    let start = len.saturating_sub(mac_len + 256);
+    for (i, &b) in record.iter().enumerate().skip(start) {
+        for (k, r) in received[..mac_len].iter_mut().enumerate() {
+            *r |= ct_select_u8(ct_usize_eq(i, data_len + k), b, 0);
+        }
+    }
+    let mut computed = [0u8;48];
+    mac(data_len, &mut computed[..mac_len]);
+    dummy(kind.inner_blocks(len - mac_len) - kind.inner_blocks(data_len));
+
+    let valid = pad_ok & ct_eq_slice(&received[..mac_len], &computed[..mac_len]);
+    (valid, ct_select_usize(valid, data_len, 0))
+}
+#[cfg(test)]
+fn toy_tls_mac(data: &[u8], tag: &mut [u8]) {
+    for (k, t) in tag.iter_mut().enumerate() {
+        let mut acc = (data.len() as u8) ^ (k as u8);
+        for &d in data.iter() {
+            acc = acc.rotate_left(1) ^ d;
+        }
+        *t = acc;
+    }
+}
+#[test]
+fn test_ct_tls_cbc_verify() {
+    let kinds = [TlsCbcMac::HmacSha1, TlsCbcMac::HmacSha256, TlsCbcMac::HmacSha384];
+    for &kind in kinds.iter() {
+        let mac_len = kind.mac_len();
+        let mut record = [0u8;320];
+        let len = 320;
+        for &pad in [0usize, 1, 15, 255].iter() {
+            let data_len = len - mac_len - pad - 1;
+            for i in 0..data_len {
+                record[i] = (i * 7) as u8;
+            }
+            let mut tag = [0u8;48];
+            toy_tls_mac(&record[..data_len], &mut tag[..mac_len]);
+            record[data_len..(data_len + mac_len)].copy_from_slice(&tag[..mac_len]);
+            for b in record[(data_len + mac_len)..].iter_mut() {
+                *b = pad as u8;
+            }
+            let mut real = 0;
+            let mut extra = 0;
+            let res = ct_tls_cbc_verify(&record, kind, |l, t| {
+                real = kind.inner_blocks(l);
+                toy_tls_mac(&record[..l], t);
+            }, |n| extra = n);
+            assert_eq!( res, (true, data_len));
+            assert_eq!( real + extra, kind.inner_blocks(len - mac_len));
+
+            //flip a padding byte, a tag byte and a data byte
+            for &at in [len - 2, data_len, 0].iter() {
+                let mut bad = record;
+                bad[at] ^= 1;
+                let mut real = 0;
+                let mut extra = 0;
+                let res = ct_tls_cbc_verify(&bad, kind, |l, t| {
+                    real = kind.inner_blocks(l);
+                    toy_tls_mac(&bad[..l], t);
+                }, |n| extra = n);
+                assert_eq!( res, (false, 0));
+                assert_eq!( real + extra, kind.inner_blocks(len - mac_len));
+            }
+        }
+        //padding longer than the record
+        let mut short = [0xFFu8;64];
+        short[0] = 0;
+        assert_eq!( ct_tls_cbc_verify(&short, kind, |l, t| toy_tls_mac(&short[..l], t), |_| {}), (false, 0));
+        assert_eq!( ct_tls_cbc_verify(&short[..mac_len], kind, |_, _| {}, |_| {}), (false, 0));
+    }
+}
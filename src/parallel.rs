@@ -0,0 +1,67 @@
+//Copyright 2016 William Cody Laeder
+//
+//Licensed under the Apache License, Version 2.0 (the "License");
+//you may not use this file except in compliance with the License.
+//you may obtain a copy of the License at
+//
+//  http://www.apache.org/licenses/LICENSE-2.0
+//
+//Unless required by applicable law or agreed to in writing, software
+//distrubuted under the License is distrubuted on as "AS IS" BASIS,
+//WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//See the License for the specific language governing permissions and
+//limitations under the License.
+
+
+//!Parallel comparison of huge buffers
+//!
+//!Enabled with the `rayon` feature, which requires `std`.
+//!
+//!The buffers are split into fixed size chunks of `CHUNK_LEN` bytes.
+//!Every chunk is fully accumulated on some worker thread and the
+//!per chunk accumulators are OR'd together, the single comparison
+//!happens at the very end. How the work is scheduled is decided by
+//!the chunking granularity and the thread pool, never by the data: no
+//!chunk can finish early because it found a difference.
+
+use rayon::prelude::*;
+use super::ct_u8_eq;
+
+///Granularity of the parallel split, in bytes.
+pub const CHUNK_LEN: usize = 64 * 1024;
+
+///Check the equality of byte slices using every core.
+///
+///Meant for multi megabyte inputs such as disk images, for anything
+///smaller the thread handoff costs more than it saves. Returns
+///`false` if X and Y are not equal length.
+pub fn ct_eq_slice_parallel(x: &[u8], y: &[u8]) -> bool {
+    if x.len() != y.len() {
+        return false;
+    }
+    let flag = x.par_chunks(CHUNK_LEN)
+        .zip(y.par_chunks(CHUNK_LEN))
+        .map(|(x_chunk, y_chunk)| {
+            let mut flag: u8 = 0;
+            for i in 0..x_chunk.len() {
+                flag |= x_chunk[i] ^ y_chunk[i];
+            }
+            flag
+        })
+        .reduce(|| 0, |a, b| a | b);
+    ct_u8_eq(flag, 0)
+}
+#[test]
+fn test_ct_eq_slice_parallel() {
+    let len = CHUNK_LEN * 3 + 17;
+    let x = std::vec![0x5Au8; len];
+    let mut y = x.clone();
+    assert_eq!( ct_eq_slice_parallel(&x, &y), true);
+    y[len - 1] ^= 1;
+    assert_eq!( ct_eq_slice_parallel(&x, &y), false);
+    y[len - 1] ^= 1;
+    y[CHUNK_LEN] ^= 0x80;
+    assert_eq!( ct_eq_slice_parallel(&x, &y), false);
+    assert_eq!( ct_eq_slice_parallel(&x, &y[1..]), false);
+    assert_eq!( ct_eq_slice_parallel(&[], &[]), true);
+}
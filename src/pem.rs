@@ -0,0 +1,122 @@
+//Copyright 2016 William Cody Laeder
+//
+//Licensed under the Apache License, Version 2.0 (the "License");
+//you may not use this file except in compliance with the License.
+//you may obtain a copy of the License at
+//
+//  http://www.apache.org/licenses/LICENSE-2.0
+//
+//Unless required by applicable law or agreed to in writing, software
+//distrubuted under the License is distrubuted on as "AS IS" BASIS,
+//WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//See the License for the specific language governing permissions and
+//limitations under the License.
+
+
+//!PEM decoding
+//!
+//!Strips the armor of a PEM document and runs the body through the
+//!constant time base64 decoder. The armor, the label and the line
+//!layout are public and are parsed normally, only the body characters
+//!are treated as secret. Needs the `alloc` feature.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use super::base64::ct_base64_decode;
+
+/*
+ * Lines without their terminator, a trailing '\r' is dropped so both
+ * line endings work.
+ */
+fn lines(pem: &[u8]) -> impl Iterator<Item = &[u8]> {
+    pem.split(|c| *c == b'\n').map(|line| {
+        match line.last() {
+            Some(&b'\r') => &line[..line.len() - 1],
+            _ => line,
+        }
+    })
+}
+fn is_armor(line: &[u8], kind: &[u8], label: &[u8]) -> bool {
+    let mut want = Vec::with_capacity(16 + kind.len() + label.len());
+    want.extend_from_slice(b"-----");
+    want.extend_from_slice(kind);
+    want.push(b' ');
+    want.extend_from_slice(label);
+    want.extend_from_slice(b"-----");
+    line == &want[..]
+}
+/*
+ * Overwrite a buffer that held secret material before it is freed.
+ */
+fn clear(buf: &mut [u8]) {
+    for b in buf.iter_mut() {
+        unsafe{::core::ptr::write_volatile(b, 0)};
+    }
+}
+
+///Decode a PEM document with the given label, e.g. `PRIVATE KEY`.
+///
+///The first and last non-empty lines must be the `BEGIN` and `END`
+///armor for `label`, anything else returns `false` right away as the
+///armor is public. Every line between them is base64 body, joined and
+///decoded in one pass by `ct_base64_decode`. RFC 1421 headers are not
+///supported and make the body invalid.
+///
+///Returns if the body was valid base64 and the decoded bytes. If it
+///was invalid the contents of the bytes are meaningless. The joined
+///body is cleared before it is freed, its whole capacity and not only
+///the bytes in use.
+pub fn ct_pem_decode(pem: &[u8], label: &[u8]) -> (bool, Vec<u8>) {
+    let mut all = lines(pem).filter(|line| !line.is_empty());
+    let first = all.next();
+    let (first, last, count) = {
+        let mut last = None;
+        let mut count = 0;
+        for line in all {
+            last = Some(line);
+            count += 1;
+        }
+        (first, last, count)
+    };
+    let armored = match (first, last) {
+        (Some(f), Some(l)) => is_armor(f, b"BEGIN", label) && is_armor(l, b"END", label),
+        _ => false,
+    };
+    if !armored {
+        return (false, Vec::new());
+    }
+    let mut body: Vec<u8> = Vec::with_capacity(pem.len());
+    for line in lines(pem).filter(|line| !line.is_empty()).skip(1).take(count - 1) {
+        body.extend_from_slice(line);
+    }
+    let mut out = vec![0; (body.len() / 4) * 3];
+    let (valid, len) = ct_base64_decode(&mut out, &body);
+    //wipe the spare capacity as well, not just what was used
+    let cap = body.capacity();
+    body.resize(cap, 0);
+    clear(&mut body);
+    out.truncate(len);
+    (valid, out)
+}
+#[test]
+fn test_ct_pem_decode() {
+    let pem = b"-----BEGIN TEST KEY-----\n\
+        Zm9vYmFyZm9v\r\n\
+        YmFy\n\
+        -----END TEST KEY-----\n";
+    assert_eq!( ct_pem_decode(pem, b"TEST KEY"), (true, b"foobarfoobar".to_vec()));
+    assert_eq!( ct_pem_decode(pem, b"OTHER KEY").0, false);
+    let pem = b"\n-----BEGIN TEST KEY-----\nZm9vYg==\n-----END TEST KEY-----";
+    assert_eq!( ct_pem_decode(pem, b"TEST KEY"), (true, b"foob".to_vec()));
+    let pem = b"-----BEGIN TEST KEY-----\n-----END TEST KEY-----\n";
+    assert_eq!( ct_pem_decode(pem, b"TEST KEY"), (true, Vec::new()));
+    let bad: [&[u8];4] = [
+        b"-----BEGIN TEST KEY-----\nZm9v!mFy\n-----END TEST KEY-----\n",
+        b"-----BEGIN TEST KEY-----\nProc-Type: 4,ENCRYPTED\nZm9v\n-----END TEST KEY-----\n",
+        b"-----BEGIN TEST KEY-----\nZm9vYmFy\n",
+        b"",
+    ];
+    for b in bad.iter() {
+        assert_eq!( ct_pem_decode(b, b"TEST KEY").0, false);
+    }
+}
@@ -0,0 +1,147 @@
+//Copyright 2016 William Cody Laeder
+//
+//Licensed under the Apache License, Version 2.0 (the "License");
+//you may not use this file except in compliance with the License.
+//you may obtain a copy of the License at
+//
+//  http://www.apache.org/licenses/LICENSE-2.0
+//
+//Unless required by applicable law or agreed to in writing, software
+//distrubuted under the License is distrubuted on as "AS IS" BASIS,
+//WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//See the License for the specific language governing permissions and
+//limitations under the License.
+
+
+//!Vectorized slice operations
+//!
+//!Enabled with the `simd` feature. These are byte slice versions of
+//!`ct_eq_slice`, `ct_copy` and `ct_xor_slice` built on `core::simd`.
+//!The vector loop and the scalar tail only depend on the length of
+//!the input, the lanes are never inspected until the single
+//!comparison at the very end.
+
+use core::simd::Simd;
+use core::simd::num::SimdUint;
+use super::{ct_select_u8, ct_u8_eq};
+
+const LANES: usize = 32;
+type Vector = Simd<u8, LANES>;
+
+///Check the equality of byte slices.
+///
+///Every lane is OR'd into one accumulator which is reduced and
+///compared once at the end.
+pub fn ct_eq_slice(x: &[u8], y: &[u8]) -> bool {
+    let len = x.len();
+    if len != y.len() {
+        return false;
+    }
+    let x_chunks = x.chunks_exact(LANES);
+    let y_chunks = y.chunks_exact(LANES);
+    let x_tail = x_chunks.remainder();
+    let y_tail = y_chunks.remainder();
+    let mut acc: Vector = Simd::splat(0);
+    for (x_chunk, y_chunk) in x_chunks.zip(y_chunks) {
+        acc |= Vector::from_slice(x_chunk) ^ Vector::from_slice(y_chunk);
+    }
+    let mut flag: u8 = acc.reduce_or();
+    for i in 0..x_tail.len() {
+        flag |= x_tail[i] ^ y_tail[i];
+    }
+    ct_u8_eq(flag, 0)
+}
+///Optional buffer copying.
+///
+///IF flag == True THEN X will be set to Y
+///
+///If flag == False THEN X is unchanged
+///
+///The flag is turned into a splatted mask, every lane is blended.
+///
+///#Panic:
+///
+///This function will panic if X and Y are not equal length.
+pub fn ct_copy(flag: bool, x: &mut [u8], y: &[u8]) {
+    let len = x.len();
+    if len != y.len() {
+        panic!("Consistent Time: Attempted to copy between non-equal lens");
+    }
+    let mask = ct_select_u8(flag, 0xFF, 0);
+    let wide: Vector = Simd::splat(mask);
+    let mut x_chunks = x.chunks_exact_mut(LANES);
+    let y_chunks = y.chunks_exact(LANES);
+    let y_tail = y_chunks.remainder();
+    for (x_chunk, y_chunk) in (&mut x_chunks).zip(y_chunks) {
+        let x_val = Vector::from_slice(x_chunk);
+        let y_val = Vector::from_slice(y_chunk);
+        ((y_val & wide) | (x_val & !wide)).copy_to_slice(x_chunk);
+    }
+    let x_tail = x_chunks.into_remainder();
+    for i in 0..x_tail.len() {
+        x_tail[i] = (y_tail[i] & mask) | (x_tail[i] & !mask);
+    }
+}
+///XOR a byte slice into another.
+///
+///X will be set to X ^ Y.
+///
+///#Panic:
+///
+///This function will panic if X and Y are not equal length.
+pub fn ct_xor_slice(x: &mut [u8], y: &[u8]) {
+    let len = x.len();
+    if len != y.len() {
+        panic!("Consistent Time: Attempted to xor between non-equal lens");
+    }
+    let mut x_chunks = x.chunks_exact_mut(LANES);
+    let y_chunks = y.chunks_exact(LANES);
+    let y_tail = y_chunks.remainder();
+    for (x_chunk, y_chunk) in (&mut x_chunks).zip(y_chunks) {
+        let val = Vector::from_slice(x_chunk) ^ Vector::from_slice(y_chunk);
+        val.copy_to_slice(x_chunk);
+    }
+    let x_tail = x_chunks.into_remainder();
+    for i in 0..x_tail.len() {
+        x_tail[i] ^= y_tail[i];
+    }
+}
+#[test]
+fn test_simd_ct_eq_slice() {
+    let mut x: [u8;100] = [0;100];
+    for i in 0..100 {
+        x[i] = (i * 3) as u8;
+    }
+    for len in 0..100 {
+        let mut y = x;
+        assert_eq!( ct_eq_slice(&x[..len], &y[..len]), true);
+        for i in 0..len {
+            y[i] ^= 1;
+            assert_eq!( ct_eq_slice(&x[..len], &y[..len]), false);
+            y[i] ^= 1;
+        }
+    }
+    assert_eq!( ct_eq_slice(&x[..40], &x[..41]), false);
+}
+#[test]
+fn test_simd_ct_copy() {
+    let y: [u8;70] = [0xAB;70];
+    let mut x: [u8;70] = [0;70];
+    ct_copy(false, &mut x, &y);
+    assert_eq!( &x[..], &[0;70][..]);
+    ct_copy(true, &mut x, &y);
+    assert_eq!( &x[..], &y[..]);
+}
+#[test]
+fn test_simd_ct_xor_slice() {
+    let y: [u8;70] = [0x0F;70];
+    let mut x: [u8;70] = [0xFF;70];
+    ct_xor_slice(&mut x, &y);
+    assert_eq!( &x[..], &[0xF0;70][..]);
+}
+#[test]
+#[should_panic]
+fn test_simd_ct_copy_panic() {
+    let mut x: [u8;40] = [0;40];
+    ct_copy(true, &mut x, &[0;39]);
+}
@@ -0,0 +1,211 @@
+//Copyright 2016 William Cody Laeder
+//
+//Licensed under the Apache License, Version 2.0 (the "License");
+//you may not use this file except in compliance with the License.
+//you may obtain a copy of the License at
+//
+//  http://www.apache.org/licenses/LICENSE-2.0
+//
+//Unless required by applicable law or agreed to in writing, software
+//distrubuted under the License is distrubuted on as "AS IS" BASIS,
+//WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//See the License for the specific language governing permissions and
+//limitations under the License.
+
+
+//!Oblivious sorting
+//!
+//!Sorting networks built from `ct_swap`. The sequence of compare and
+//!exchange operations only depends on the length of the input, so
+//!neither the memory access pattern nor the instruction trace reveal
+//!anything about the data.
+
+use super::{ConstantTime, ct_lookup, ct_lt, ct_swap};
+
+/*
+ * Compare and exchange, afterwards x[lo] <= x[hi].
+ */
+fn compare_exchange<T>(x: &mut [T], lo: usize, hi: usize)
+  where T: ConstantTime + Copy {
+    let (head, tail) = x.split_at_mut(hi);
+    let flag = ct_lt(tail[0], head[lo]);
+    ct_swap(flag, &mut head[lo], &mut tail[0]);
+}
+
+///Sort a slice in ascending order.
+///
+///This is Batcher's odd-even merge sort, generalized to any length.
+///It performs O(n log^2 n) compare and exchange operations whose
+///positions are fixed by `x.len()`.
+pub fn ct_sort<T>(x: &mut [T])
+  where T: ConstantTime + Copy {
+    let n = x.len();
+    let mut p = 1;
+    while p < n {
+        let mut k = p;
+        while k >= 1 {
+            let mut j = k % p;
+            while j + k < n {
+                let end = if k < n - j - k { k } else { n - j - k };
+                for i in 0..end {
+                    if (i + j) / (p * 2) == (i + j + k) / (p * 2) {
+                        compare_exchange(x, i + j, i + j + k);
+                    }
+                }
+                j += k * 2;
+            }
+            k /= 2;
+        }
+        p *= 2;
+    }
+}
+#[cfg(test)]
+fn lcg(state: &mut u64) -> u64 {
+    *state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+    *state >> 33
+}
+#[test]
+fn test_ct_sort() {
+    let mut state: u64 = 1;
+    for n in 0..40 {
+        let mut x: [u32;40] = [0;40];
+        for i in 0..n {
+            x[i] = (lcg(&mut state) % 16) as u32;
+        }
+        let mut want = x;
+        want[..n].sort();
+        ct_sort(&mut x[..n]);
+        assert_eq!( x, want);
+    }
+    let mut y: [u8;5] = [255,0,255,1,0];
+    ct_sort(&mut y);
+    assert_eq!( y, [0,0,1,255,255]);
+}
+
+/*
+ * Bitonic merge of x[lo..lo+n] for any n, in ascending order.
+ * m is the greatest power of two below n, see H. W. Lang's notes on
+ * bitonic sorting networks for arbitrary n.
+ */
+fn bitonic_merge<T>(x: &mut [T], lo: usize, n: usize)
+  where T: ConstantTime + Copy {
+    if n <= 1 {
+        return;
+    }
+    let mut m = 1;
+    while m * 2 < n {
+        m *= 2;
+    }
+    for i in lo..(lo + n - m) {
+        compare_exchange(x, i, i + m);
+    }
+    bitonic_merge(x, lo, m);
+    bitonic_merge(x, lo + m, n - m);
+}
+
+///Merge two sorted slices into `dst`.
+///
+///`x` and `y` must each be sorted in ascending order, `dst` will hold
+///all of their elements in ascending order. `x` is written reversed
+///in front of `y` and the resulting bitonic sequence is merged by a
+///network whose shape only depends on the lengths.
+///
+///#Panic:
+///
+///This function will panic if `dst` is not exactly as long as `x` and
+///`y` combined.
+pub fn ct_merge<T>(dst: &mut [T], x: &[T], y: &[T])
+  where T: ConstantTime + Copy {
+    let x_len = x.len();
+    let n = dst.len();
+    if n != x_len + y.len() {
+        panic!("Consistent Time: Merge destination has the wrong length");
+    }
+    for i in 0..x_len {
+        dst[i] = x[x_len - 1 - i];
+    }
+    dst[x_len..].copy_from_slice(y);
+    bitonic_merge(dst, 0, n);
+}
+#[test]
+fn test_ct_merge() {
+    let mut state: u64 = 7;
+    for x_len in 0..12 {
+        for y_len in 0..12 {
+            for _ in 0..8 {
+                let mut x: [u16;12] = [0;12];
+                let mut y: [u16;12] = [0;12];
+                for i in 0..x_len {
+                    x[i] = (lcg(&mut state) % 10) as u16;
+                }
+                for i in 0..y_len {
+                    y[i] = (lcg(&mut state) % 10) as u16;
+                }
+                x[..x_len].sort();
+                y[..y_len].sort();
+                let mut dst: [u16;24] = [0;24];
+                let mut want: [u16;24] = [0;24];
+                want[..x_len].copy_from_slice(&x[..x_len]);
+                want[x_len..(x_len + y_len)].copy_from_slice(&y[..y_len]);
+                want[..(x_len + y_len)].sort();
+                ct_merge(&mut dst[..(x_len + y_len)], &x[..x_len], &y[..y_len]);
+                assert_eq!( dst, want);
+            }
+        }
+    }
+}
+#[test]
+#[should_panic]
+fn test_ct_merge_panic() {
+    let mut dst: [u8;2] = [0;2];
+    ct_merge(&mut dst, &[1], &[2,3]);
+}
+
+///Oblivious k-th order statistic.
+///
+///Returns the element which would be at index `k` if `x` was sorted in
+///ascending order. A copy of `x` is sorted with `ct_sort` and read with
+///`ct_lookup`, so neither the ranks nor `k` are revealed. An out of
+///range `k` returns the smallest element.
+///
+///#Panic:
+///
+///This function will panic if `N` is zero.
+pub fn ct_kth<T, const N: usize>(x: &[T; N], k: usize) -> T
+  where T: ConstantTime + Copy {
+    let mut sorted = *x;
+    ct_sort(&mut sorted);
+    ct_lookup(&sorted, k)
+}
+///Oblivious median.
+///
+///For an even `N` this is the lower median, `ct_kth(x, (N - 1) / 2)`.
+///
+///#Panic:
+///
+///This function will panic if `N` is zero.
+pub fn ct_median<T, const N: usize>(x: &[T; N]) -> T
+  where T: ConstantTime + Copy {
+    ct_kth(x, N.wrapping_sub(1) / 2)
+}
+#[test]
+fn test_ct_kth() {
+    let x: [u64;7] = [50,10,40,70,20,60,30];
+    for k in 0..7 {
+        assert_eq!( ct_kth(&x, k), ((k + 1) * 10) as u64);
+    }
+    assert_eq!( ct_kth(&x, 7), 10);
+    assert_eq!( x, [50,10,40,70,20,60,30]);
+}
+#[test]
+fn test_ct_median() {
+    assert_eq!( ct_median(&[3u8,1,2]), 2);
+    assert_eq!( ct_median(&[4u8,1,3,2]), 2);
+    assert_eq!( ct_median(&[9u32]), 9);
+}
+#[test]
+#[should_panic]
+fn test_ct_median_panic() {
+    let x: [u8;0] = [];
+    ct_median(&x);
+}
@@ -0,0 +1,504 @@
+//Copyright 2016 William Cody Laeder
+//
+//Licensed under the Apache License, Version 2.0 (the "License");
+//you may not use this file except in compliance with the License.
+//you may obtain a copy of the License at
+//
+//  http://www.apache.org/licenses/LICENSE-2.0
+//
+//Unless required by applicable law or agreed to in writing, software
+//distrubuted under the License is distrubuted on as "AS IS" BASIS,
+//WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//See the License for the specific language governing permissions and
+//limitations under the License.
+
+
+//!Oblivious tables
+//!
+//!Tables indexed by secrets. Every access scans every entry, so the
+//!memory access pattern never depends on the index.
+
+use super::{ConstantTime, ct_lookup, ct_store, ct_usize_eq, ct_u8_eq, ct_select_u8, ct_select_u64};
+use super::arch::prefetch;
+
+///A table whose only access path is a full scan.
+///
+///Meant for S-boxes and other small precomputed tables of block
+///ciphers and KDFs. There is no `Index` implementation and the entries
+///can not be borrowed, so no code path can read a single entry
+///directly.
+#[derive(Clone)]
+pub struct CtLookupTable<T, const N: usize> {
+    entries: [T; N],
+}
+impl<T, const N: usize> CtLookupTable<T, N>
+  where T: ConstantTime + Copy {
+    ///Build a table from its entries.
+    pub fn new(entries: [T; N]) -> CtLookupTable<T, N> {
+        CtLookupTable{ entries }
+    }
+    ///Number of entries, this is public.
+    pub fn len(&self) -> usize {
+        N
+    }
+    ///If the table has no entries, this is public.
+    pub fn is_empty(&self) -> bool {
+        N == 0
+    }
+    ///Read the entry at a secret index.
+    ///
+    ///See `ct_lookup`. An out of range index returns the first entry.
+    ///
+    ///#Panic:
+    ///
+    ///This function will panic if the table is empty.
+    pub fn get(&self, index: usize) -> T {
+        ct_lookup(&self.entries, index)
+    }
+    ///Read many entries at secret indexes.
+    ///
+    ///`out[i]` will be set to the entry at `indexes[i]`. Each read is a
+    ///full scan of the table.
+    ///
+    ///#Panic:
+    ///
+    ///This function will panic if `indexes` and `out` are not equal
+    ///length, or if the table is empty.
+    pub fn get_many(&self, indexes: &[usize], out: &mut [T]) {
+        let len = indexes.len();
+        if len != out.len() {
+            panic!("Consistent Time: Attempted lookup between non-equal lens");
+        }
+        for i in 0..len {
+            out[i] = ct_lookup(&self.entries, indexes[i]);
+        }
+    }
+}
+#[test]
+fn test_ct_lookup_table() {
+    let table = CtLookupTable::new([0x63u8,0x7C,0x77,0x7B]);
+    assert_eq!( table.len(), 4);
+    assert_eq!( table.is_empty(), false);
+    assert_eq!( table.get(0), 0x63);
+    assert_eq!( table.get(3), 0x7B);
+    assert_eq!( table.get(4), 0x63);
+    let mut out: [u8;3] = [0;3];
+    table.get_many(&[2,1,2], &mut out);
+    assert_eq!( out, [0x77,0x7C,0x77]);
+}
+#[test]
+#[should_panic]
+fn test_ct_lookup_table_panic() {
+    let table = CtLookupTable::new([1u32,2,3]);
+    let mut out: [u32;1] = [0];
+    table.get_many(&[0,1], &mut out);
+}
+
+///Oblivious read of a matrix at a secret row and column.
+///
+///Every entry of every row is read, only the one at `(row, col)` is
+///kept. Meant for precomputed multiples indexed by window value and
+///position.
+///
+///An out of range `row` or `col` returns `table[0][0]`.
+///
+///#Panic:
+///
+///This function will panic if the matrix is empty.
+pub fn ct_lookup_2d<T, const C: usize>(table: &[[T; C]], row: usize, col: usize) -> T
+  where T: ConstantTime + Copy {
+    if table.is_empty() || C == 0 {
+        panic!("Consistent Time: Attempted lookup in an empty table");
+    }
+    let mut val = table[0][0];
+    for r in 0..table.len() {
+        let row_hit = ct_usize_eq(r, row);
+        for c in 0..C {
+            let hit = row_hit & ct_usize_eq(c, col);
+            val = <T as ConstantTime>::ct_select(hit, table[r][c], val);
+        }
+    }
+    val
+}
+#[test]
+fn test_ct_lookup_2d() {
+    let table: [[u64;3];2] = [[1,2,3],[4,5,6]];
+    for r in 0..2 {
+        for c in 0..3 {
+            assert_eq!( ct_lookup_2d(&table, r, c), table[r][c]);
+        }
+    }
+    assert_eq!( ct_lookup_2d(&table, 2, 0), 1);
+    assert_eq!( ct_lookup_2d(&table, 0, 3), 1);
+}
+#[test]
+#[should_panic]
+fn test_ct_lookup_2d_panic() {
+    let table: [[u8;0];2] = [[],[]];
+    ct_lookup_2d(&table, 0, 0);
+}
+
+///Oblivious selection of a precomputed group element by a secret
+///window value.
+///
+///All `W` entries are read in full and masked, the one at `window` is
+///OR'd into the result. This is the table step of fixed window scalar
+///multiplication, entry `i` usually being `i * P`.
+///
+///An out of range `window` returns all zero limbs.
+pub fn ct_select_window<const L: usize, const W: usize>(table: &[[u64; L]; W], window: usize) -> [u64; L] {
+    let mut out = [0u64; L];
+    for w in 0..W {
+        let mask = ct_select_u64(ct_usize_eq(w, window), max!(u64), 0);
+        for j in 0..L {
+            out[j] |= table[w][j] & mask;
+        }
+    }
+    out
+}
+#[test]
+fn test_ct_select_window() {
+    let mut table = [[0u64;3];16];
+    for w in 0..16 {
+        table[w] = [w as u64, (w * w) as u64, u64::MAX - (w as u64)];
+    }
+    for w in 0..16 {
+        assert_eq!( ct_select_window(&table, w), table[w]);
+    }
+    assert_eq!( ct_select_window(&table, 16), [0;3]);
+    assert_eq!( ct_select_window(&table, usize::MAX), [0;3]);
+}
+
+///A small array whose reads and writes use secret indexes.
+///
+///Drop in replacement for `[T; N]` when the access pattern must not
+///leak. `read`, `write` and `swap` all scan the full array, out of
+///range indexes read the first element and write nothing.
+#[derive(Clone)]
+pub struct ObliviousArray<T, const N: usize> {
+    entries: [T; N],
+}
+impl<T, const N: usize> ObliviousArray<T, N>
+  where T: ConstantTime + Copy {
+    ///Build an array from its initial contents.
+    pub fn new(entries: [T; N]) -> ObliviousArray<T, N> {
+        ObliviousArray{ entries }
+    }
+    ///Number of elements, this is public.
+    pub fn len(&self) -> usize {
+        N
+    }
+    ///If the array has no elements, this is public.
+    pub fn is_empty(&self) -> bool {
+        N == 0
+    }
+    ///Read the element at a secret index.
+    ///
+    ///#Panic:
+    ///
+    ///This function will panic if the array is empty.
+    pub fn read(&self, index: usize) -> T {
+        ct_lookup(&self.entries, index)
+    }
+    ///Write the element at a secret index.
+    pub fn write(&mut self, index: usize, value: T) {
+        ct_store(&mut self.entries, index, value);
+    }
+    ///Exchange the elements at two secret indexes.
+    ///
+    ///This is two full reads followed by two full writes, it does not
+    ///matter if `x == y`.
+    ///
+    ///#Panic:
+    ///
+    ///This function will panic if the array is empty.
+    pub fn swap(&mut self, x: usize, y: usize) {
+        let x_val = self.read(x);
+        let y_val = self.read(y);
+        self.write(x, y_val);
+        self.write(y, x_val);
+    }
+    ///Give back the underlying array.
+    pub fn into_inner(self) -> [T; N] {
+        self.entries
+    }
+}
+#[test]
+fn test_oblivious_array() {
+    let mut x = ObliviousArray::new([10u16,20,30,40]);
+    assert_eq!( x.len(), 4);
+    assert_eq!( x.read(2), 30);
+    x.write(1, 25);
+    assert_eq!( x.read(1), 25);
+    x.write(4, 99);
+    x.swap(0, 3);
+    assert_eq!( x.clone().into_inner(), [40,25,30,10]);
+    x.swap(2, 2);
+    assert_eq!( x.into_inner(), [40,25,30,10]);
+}
+
+///Apply a secret permutation.
+///
+///`dst[i]` will be set to `src[perm[i]]`. Every element of `dst` is
+///produced by a full scan of `src`, so the permutation is not leaked
+///through the access pattern. This costs O(n^2) selections.
+///
+///`perm` is not checked to be a permutation. An out of range entry
+///selects `src[0]`.
+///
+///#Panic:
+///
+///This function will panic if the three slices are not equal length.
+pub fn ct_permute<T>(dst: &mut [T], src: &[T], perm: &[usize])
+  where T: ConstantTime + Copy {
+    let len = dst.len();
+    if len != src.len() || len != perm.len() {
+        panic!("Consistent Time: Attempted to permute between non-equal lens");
+    }
+    for i in 0..len {
+        dst[i] = ct_lookup(src, perm[i]);
+    }
+}
+#[test]
+fn test_ct_permute() {
+    let src: [u32;5] = [10,11,12,13,14];
+    let mut dst: [u32;5] = [0;5];
+    ct_permute(&mut dst, &src, &[4,2,0,1,3]);
+    assert_eq!( dst, [14,12,10,11,13]);
+    ct_permute(&mut dst, &src, &[0,1,2,3,4]);
+    assert_eq!( dst, src);
+}
+#[test]
+#[should_panic]
+fn test_ct_permute_panic() {
+    let src: [u8;2] = [1,2];
+    let mut dst: [u8;2] = [0;2];
+    ct_permute(&mut dst, &src, &[1]);
+}
+
+///A fixed capacity key value store with oblivious access.
+///
+///This is a linear scan map, `get`, `insert` and `remove` always touch
+///every slot and combine the results with masks. Which key was hit, or
+///if any was, is not revealed by the access pattern.
+#[derive(Clone)]
+pub struct ObliviousMap<K, V, const N: usize> {
+    keys: [K; N],
+    values: [V; N],
+    used: [u8; N],
+}
+impl<K, V, const N: usize> ObliviousMap<K, V, N>
+  where K: ConstantTime + Copy + Default,
+        V: ConstantTime + Copy + Default {
+    ///Build an empty map.
+    pub fn new() -> ObliviousMap<K, V, N> {
+        ObliviousMap{
+            keys: [K::default(); N],
+            values: [V::default(); N],
+            used: [0; N],
+        }
+    }
+    ///Number of slots, this is public.
+    pub fn capacity(&self) -> usize {
+        N
+    }
+    /*
+     * If slot i holds key. Unused slots never match, even if their
+     * stale key does.
+     */
+    fn hit(&self, i: usize, key: K) -> bool {
+        let used = unsafe{::core::mem::transmute::<u8,bool>(self.used[i])};
+        used & <K as ConstantTime>::ct_eq(self.keys[i], key)
+    }
+    ///Look up a key.
+    ///
+    ///Returns if the key was present and its value, or `V::default()`
+    ///if it was not.
+    pub fn get(&self, key: K) -> (bool, V) {
+        let mut found = false;
+        let mut val = V::default();
+        for i in 0..N {
+            let hit = self.hit(i, key);
+            val = <V as ConstantTime>::ct_select(hit, self.values[i], val);
+            found |= hit;
+        }
+        (found, val)
+    }
+    ///Insert or update a key.
+    ///
+    ///An existing key has its value replaced, otherwise the first free
+    ///slot is used. Returns `false` if the key was not present and the
+    ///map is full, in which case nothing is changed.
+    pub fn insert(&mut self, key: K, value: V) -> bool {
+        let mut exists = false;
+        for i in 0..N {
+            exists |= self.hit(i, key);
+        }
+        let mut placed = false;
+        for i in 0..N {
+            let free = ct_u8_eq(self.used[i], 0);
+            let here = self.hit(i, key) | (!exists & free & !placed);
+            placed |= here;
+            self.keys[i] = <K as ConstantTime>::ct_select(here, key, self.keys[i]);
+            self.values[i] = <V as ConstantTime>::ct_select(here, value, self.values[i]);
+            self.used[i] = ct_select_u8(here, 1, self.used[i]);
+        }
+        placed
+    }
+    ///Remove a key.
+    ///
+    ///Returns if the key was present.
+    pub fn remove(&mut self, key: K) -> bool {
+        let mut found = false;
+        for i in 0..N {
+            let hit = self.hit(i, key);
+            self.used[i] = ct_select_u8(hit, 0, self.used[i]);
+            found |= hit;
+        }
+        found
+    }
+}
+impl<K, V, const N: usize> Default for ObliviousMap<K, V, N>
+  where K: ConstantTime + Copy + Default,
+        V: ConstantTime + Copy + Default {
+    fn default() -> ObliviousMap<K, V, N> {
+        ObliviousMap::new()
+    }
+}
+#[test]
+fn test_oblivious_map() {
+    let mut map: ObliviousMap<u64, u32, 3> = ObliviousMap::new();
+    assert_eq!( map.capacity(), 3);
+    assert_eq!( map.get(7), (false, 0));
+    assert_eq!( map.insert(7, 70), true);
+    assert_eq!( map.insert(8, 80), true);
+    assert_eq!( map.get(7), (true, 70));
+    assert_eq!( map.insert(7, 71), true);
+    assert_eq!( map.get(7), (true, 71));
+    assert_eq!( map.insert(9, 90), true);
+    assert_eq!( map.insert(10, 100), false);
+    assert_eq!( map.get(10), (false, 0));
+    assert_eq!( map.remove(8), true);
+    assert_eq!( map.remove(8), false);
+    assert_eq!( map.get(8), (false, 0));
+    assert_eq!( map.insert(10, 100), true);
+    assert_eq!( map.get(10), (true, 100));
+    assert_eq!( map.get(9), (true, 90));
+    assert_eq!( map.get(7), (true, 71));
+}
+
+///Bytes scanned per block by `ct_lookup_prefetch`.
+pub const PREFETCH_BLOCK: usize = 512;
+
+///Oblivious read at a secret index for tables larger than L1.
+///
+///Same result and guarantees as `ct_lookup`, every element is read in
+///the same order for every index. The table is streamed in blocks of
+///`PREFETCH_BLOCK` bytes and the next block is prefetched before the
+///current one is scanned, so the scan is not stalled on cache misses.
+///The prefetch addresses only depend on the block position.
+///
+///#Panic:
+///
+///This function will panic if `x` is empty.
+pub fn ct_lookup_prefetch<T>(x: &[T], index: usize) -> T
+  where T: ConstantTime + Copy {
+    if x.is_empty() {
+        panic!("Consistent Time: Attempted lookup in an empty slice");
+    }
+    let size = ::core::mem::size_of::<T>();
+    let per_block = if size >= PREFETCH_BLOCK { 1 } else { PREFETCH_BLOCK / size };
+    let line = 64 / if size > 64 { 64 } else { size };
+    let mut val = x[0];
+    let mut base = 0;
+    for block in x.chunks(per_block) {
+        let next = x.as_ptr().wrapping_add(base + per_block);
+        let mut off = 0;
+        while off < per_block {
+            prefetch(next.wrapping_add(off));
+            off += line;
+        }
+        for (j, elem) in block.iter().enumerate() {
+            val = <T as ConstantTime>::ct_select(ct_usize_eq(base + j, index), *elem, val);
+        }
+        base += per_block;
+    }
+    val
+}
+#[test]
+fn test_ct_lookup_prefetch() {
+    let mut table: [u64;2048] = [0;2048];
+    for i in 0..2048 {
+        table[i] = (i as u64).wrapping_mul(0x9E3779B97F4A7C15);
+    }
+    for &i in [0usize, 1, 63, 64, 65, 1000, 2047].iter() {
+        assert_eq!( ct_lookup_prefetch(&table, i), table[i]);
+        assert_eq!( ct_lookup_prefetch(&table, i), ct_lookup(&table, i));
+    }
+    assert_eq!( ct_lookup_prefetch(&table, 2048), table[0]);
+    let small: [u8;3] = [7,8,9];
+    assert_eq!( ct_lookup_prefetch(&small, 2), 9);
+}
+
+///Oblivious gather.
+///
+///`dst[i]` will be set to `src[indexes[i]]`. Every element of `src` is
+///read for every element of `dst`. An out of range index selects
+///`src[0]`.
+///
+///#Panic:
+///
+///This function will panic if `dst` and `indexes` are not equal
+///length, or if `src` is empty while `dst` is not.
+pub fn ct_gather<T>(dst: &mut [T], src: &[T], indexes: &[usize])
+  where T: ConstantTime + Copy {
+    let len = dst.len();
+    if len != indexes.len() {
+        panic!("Consistent Time: Attempted to gather between non-equal lens");
+    }
+    for i in 0..len {
+        dst[i] = ct_lookup(src, indexes[i]);
+    }
+}
+///Oblivious scatter.
+///
+///`dst[indexes[i]]` will be set to `src[i]`. Every element of `dst` is
+///rewritten for every element of `src`, when two indexes collide the
+///later one wins. Out of range indexes write nothing, elements of
+///`dst` which are not targeted keep their value.
+///
+///#Panic:
+///
+///This function will panic if `src` and `indexes` are not equal
+///length.
+pub fn ct_scatter<T>(dst: &mut [T], indexes: &[usize], src: &[T])
+  where T: ConstantTime + Copy {
+    let len = src.len();
+    if len != indexes.len() {
+        panic!("Consistent Time: Attempted to scatter between non-equal lens");
+    }
+    for i in 0..len {
+        ct_store(dst, indexes[i], src[i]);
+    }
+}
+#[test]
+fn test_ct_gather() {
+    let src: [u16;4] = [100,200,300,400];
+    let mut dst: [u16;6] = [0;6];
+    ct_gather(&mut dst, &src, &[3,3,0,1,2,9]);
+    assert_eq!( dst, [400,400,100,200,300,100]);
+}
+#[test]
+fn test_ct_scatter() {
+    let mut dst: [u16;5] = [0;5];
+    ct_scatter(&mut dst, &[4,0,2,9], &[1,2,3,4]);
+    assert_eq!( dst, [2,0,3,0,1]);
+    ct_scatter(&mut dst, &[1,1], &[7,8]);
+    assert_eq!( dst, [2,8,3,0,1]);
+}
+#[test]
+#[should_panic]
+fn test_ct_scatter_panic() {
+    let mut dst: [u8;2] = [0;2];
+    ct_scatter(&mut dst, &[0], &[1,2]);
+}